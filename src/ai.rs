@@ -0,0 +1,411 @@
+//! Built-in bots, as demos and as baselines to measure other controllers against
+
+use std::collections::VecDeque;
+
+use crate::controller::Controller;
+use crate::world::{MovementDirection, WorldView};
+
+const DIRECTIONS: [MovementDirection; 4] = [
+    MovementDirection::Up,
+    MovementDirection::Left,
+    MovementDirection::Down,
+    MovementDirection::Right,
+];
+
+/// Whether the head can move onto `tile` on the next tick: tails move out of the way,
+/// every other segment does not
+fn is_free(view: &WorldView, tile: [usize; 2]) -> bool {
+    !view.is_blocked(tile)
+}
+
+/// Whether an opponent at least as long could move its head onto `tile` next tick, which would
+/// kill the snake in a head-on collision
+fn is_contested(view: &WorldView, tile: [usize; 2]) -> bool {
+    view.opponents()
+        .filter(|opponent| opponent.length() >= view.snake_body.len())
+        .filter_map(|opponent| opponent.head())
+        .any(|head| DIRECTIONS.iter().any(|direction| view.next_tile(head, *direction) == Some(tile)))
+}
+
+/// The headings the snake can take next tick without dying, in a fixed order. Steps an opponent
+/// could contest head-on are only included when there is nothing else.
+fn safe_directions(view: &WorldView) -> Vec<(MovementDirection, [usize; 2])> {
+    let head = match view.head() {
+        Some(head) => head,
+        None => return Vec::new(),
+    };
+
+    let safe: Vec<(MovementDirection, [usize; 2])> = DIRECTIONS.iter()
+        .filter(|direction| **direction != view.heading.opposite())
+        .filter_map(|direction| view.next_tile(head, *direction).map(|tile| (*direction, tile)))
+        .filter(|(_, tile)| is_free(view, *tile))
+        .collect();
+
+    let uncontested: Vec<(MovementDirection, [usize; 2])> = safe.iter()
+        .copied()
+        .filter(|(_, tile)| !is_contested(view, *tile))
+        .collect();
+    if uncontested.is_empty() { safe } else { uncontested }
+}
+
+/// Distance between two tiles along one axis, taking the shorter way round if the axis wraps
+fn axis_distance(a: usize, b: usize, size: usize, wraps: bool) -> usize {
+    let distance = a.abs_diff(b);
+    if wraps { distance.min(size - distance) } else { distance }
+}
+
+pub(crate) fn distance(view: &WorldView, a: [usize; 2], b: [usize; 2]) -> usize {
+    axis_distance(a[0], b[0], view.row_count, view.boundary.wraps_rows())
+        + axis_distance(a[1], b[1], view.col_count, view.boundary.wraps_cols())
+}
+
+/// How many tiles can be reached from `start` without crossing a snake
+fn reachable_area(view: &WorldView, start: [usize; 2]) -> usize {
+    let mut visited = vec![false; view.row_count * view.col_count];
+    let mut queue = VecDeque::new();
+    visited[start[0] * view.col_count + start[1]] = true;
+    queue.push_back(start);
+
+    let mut area = 0;
+    while let Some(tile) = queue.pop_front() {
+        area += 1;
+        for direction in DIRECTIONS.iter() {
+            if let Some(next) = view.next_tile(tile, *direction) {
+                let index = next[0] * view.col_count + next[1];
+                if !visited[index] && is_free(view, next) {
+                    visited[index] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    area
+}
+
+/// Takes whichever safe step brings the head closest to the food. Cheap, but happily follows
+/// the food into dead ends.
+#[derive(Debug, Clone, Default)]
+pub struct GreedyController;
+
+impl Controller for GreedyController {
+    fn next_direction(&mut self, view: &WorldView) -> MovementDirection {
+        let food = match view.food {
+            Some(food) => food,
+            None => return view.heading,
+        };
+
+        safe_directions(view).into_iter()
+            .min_by_key(|(_, tile)| distance(view, *tile, food))
+            .map(|(direction, _)| direction)
+            .unwrap_or(view.heading)
+    }
+}
+
+/// Follows the shortest path to the food found by breadth-first search, going around the body
+/// and through the edges wherever the boundary mode wraps. When the food cannot be reached it
+/// stalls by moving into the largest open area.
+#[derive(Debug, Clone, Default)]
+pub struct PathfindingController;
+
+impl PathfindingController {
+    /// The first step of a shortest path from the head to `target`. A body segment counts as
+    /// an obstacle only until its snake's tail has moved past it; dead snakes and walls never move.
+    fn first_step(view: &WorldView, target: [usize; 2]) -> Option<MovementDirection> {
+        let head = view.head()?;
+        let tile_count = view.row_count * view.col_count;
+
+        // How many ticks until each tile is vacated by the snake covering it
+        let mut vacated_after = vec![0; tile_count];
+        for snake in view.snakes.iter() {
+            for (i, segment) in snake.body.iter().enumerate() {
+                vacated_after[segment[0] * view.col_count + segment[1]] = if snake.is_alive() { i + 1 } else { usize::MAX };
+            }
+        }
+        if let Some(map) = view.map {
+            for wall in map.walls() {
+                vacated_after[wall[0] * view.col_count + wall[1]] = usize::MAX;
+            }
+        }
+
+        let mut first_steps: Vec<Option<MovementDirection>> = vec![None; tile_count];
+        let mut visited = vec![false; tile_count];
+        let mut queue = VecDeque::new();
+        visited[head[0] * view.col_count + head[1]] = true;
+        queue.push_back((head, 0));
+
+        while let Some((tile, steps)) = queue.pop_front() {
+            if tile == target {
+                return first_steps[tile[0] * view.col_count + tile[1]];
+            }
+
+            for direction in DIRECTIONS.iter() {
+                if tile == head && *direction == view.heading.opposite() {
+                    continue;
+                }
+
+                let next = match view.next_tile(tile, *direction) {
+                    Some(next) => next,
+                    None => continue,
+                };
+                let index = next[0] * view.col_count + next[1];
+                if visited[index] || vacated_after[index] > steps + 1 {
+                    continue;
+                }
+
+                visited[index] = true;
+                first_steps[index] = if tile == head { Some(*direction) } else { first_steps[tile[0] * view.col_count + tile[1]] };
+                queue.push_back((next, steps + 1));
+            }
+        }
+
+        None
+    }
+}
+
+impl Controller for PathfindingController {
+    fn next_direction(&mut self, view: &WorldView) -> MovementDirection {
+        let head = view.head();
+        let first_step = view.food.and_then(|food| PathfindingController::first_step(view, food));
+        let is_risky = |direction: MovementDirection| {
+            head.and_then(|head| view.next_tile(head, direction)).is_some_and(|tile| is_contested(view, tile))
+        };
+        if let Some(direction) = first_step.filter(|direction| !is_risky(*direction)) {
+            return direction;
+        }
+
+        safe_directions(view).into_iter()
+            .max_by_key(|(_, tile)| reachable_area(view, *tile))
+            .map(|(direction, _)| direction)
+            .unwrap_or(view.heading)
+    }
+}
+
+/// Follows a fixed cycle through every tile of the board, which guarantees the snake can never
+/// run into itself and eventually fills the board. A cycle only exists when the board has an
+/// even number of tiles; on other boards, and whenever another snake is in the way, this falls
+/// back to pathfinding.
+#[derive(Debug, Clone, Default)]
+pub struct HamiltonianController {
+    /// For each tile, the tile that follows it on the cycle, built for the current board size
+    successors: Vec<[usize; 2]>,
+    board_size: [usize; 2],
+    fallback: PathfindingController,
+}
+
+impl HamiltonianController {
+    pub fn new() -> HamiltonianController {
+        HamiltonianController::default()
+    }
+
+    /// Builds the cycle: up column 0, then back and forth over the remaining columns row by row.
+    /// Needs an even row count, so boards with an odd row count use the transposed cycle.
+    fn build_cycle(rows: usize, cols: usize) -> Option<Vec<[usize; 2]>> {
+        if rows < 2 || cols < 2 {
+            return None;
+        }
+
+        let transposed = !rows.is_multiple_of(2);
+        let (rows, cols) = if transposed { (cols, rows) } else { (rows, cols) };
+        if !rows.is_multiple_of(2) {
+            return None;
+        }
+
+        let mut cycle = Vec::with_capacity(rows * cols);
+        for i_row in 0..rows {
+            if i_row.is_multiple_of(2) {
+                cycle.extend((1..cols).map(|i_col| [i_row, i_col]));
+            } else {
+                cycle.extend((1..cols).rev().map(|i_col| [i_row, i_col]));
+            }
+        }
+        cycle.extend((0..rows).rev().map(|i_row| [i_row, 0]));
+
+        if transposed {
+            for tile in cycle.iter_mut() {
+                tile.swap(0, 1);
+            }
+        }
+
+        Some(cycle)
+    }
+
+    fn successor(&self, tile: [usize; 2]) -> [usize; 2] {
+        self.successors[tile[0] * self.board_size[1] + tile[1]]
+    }
+
+    /// Builds the successor table for the board in `view`, going round the cycle in whichever
+    /// direction does not ask the snake to reverse into its neck
+    fn prepare(&mut self, view: &WorldView) {
+        let board_size = [view.row_count, view.col_count];
+        if self.board_size == board_size && !self.successors.is_empty() {
+            return;
+        }
+
+        self.board_size = board_size;
+        self.successors.clear();
+
+        // A cycle through every tile cannot go through walls, so maps are left to the fallback
+        if view.map.is_some_and(|map| !map.walls().is_empty()) {
+            return;
+        }
+
+        let mut cycle = match HamiltonianController::build_cycle(view.row_count, view.col_count) {
+            Some(cycle) => cycle,
+            None => return,
+        };
+
+        let mut successors = vec![[0, 0]; view.row_count * view.col_count];
+        let fill = |cycle: &[[usize; 2]], successors: &mut Vec<[usize; 2]>| {
+            for (i, tile) in cycle.iter().enumerate() {
+                successors[tile[0] * view.col_count + tile[1]] = cycle[(i + 1) % cycle.len()];
+            }
+        };
+        fill(&cycle, &mut successors);
+
+        let len = view.snake_body.len();
+        if len >= 2 && successors[view.snake_body[len - 1][0] * view.col_count + view.snake_body[len - 1][1]] == view.snake_body[len - 2] {
+            cycle.reverse();
+            fill(&cycle, &mut successors);
+        }
+
+        self.successors = successors;
+    }
+}
+
+impl Controller for HamiltonianController {
+    fn next_direction(&mut self, view: &WorldView) -> MovementDirection {
+        self.prepare(view);
+        if self.successors.is_empty() {
+            return self.fallback.next_direction(view);
+        }
+
+        let head = match view.head() {
+            Some(head) => head,
+            None => return view.heading,
+        };
+        let next = self.successor(head);
+        if !is_free(view, next) {
+            return self.fallback.next_direction(view);
+        }
+
+        DIRECTIONS.iter()
+            .copied()
+            .find(|direction| view.next_tile(head, *direction) == Some(next))
+            .unwrap_or(view.heading)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{BoundaryMode, Snake, World};
+
+    /// A one-player world with the given snake, as `(body, heading)`, and food
+    fn world(rows: usize, cols: usize, snake: (Vec<[usize; 2]>, MovementDirection), food: [usize; 2]) -> World {
+        let mut world = World::with_seed(rows, cols, 1);
+        world.snakes[0] = Snake::new(snake.0, snake.1);
+        world.food = Some(food);
+        world
+    }
+
+    /// Plays `ticks` ticks with `controller` steering snake 0, checking every step with `check`
+    fn play<C, F>(world: &mut World, controller: &mut C, ticks: u64, mut check: F)
+        where C: Controller, F: FnMut(&WorldView, MovementDirection)
+    {
+        for _ in 0..ticks {
+            if world.is_game_over() {
+                break;
+            }
+
+            let direction = controller.next_direction(&world.view(0));
+            check(&world.view(0), direction);
+            world.snakes[0].queue_direction(direction);
+            world.step();
+        }
+    }
+
+    #[test]
+    fn pathfinding_never_steps_into_a_body() {
+        for seed in 0..20 {
+            let mut world = World::with_seed(8, 8, seed);
+            play(&mut world, &mut PathfindingController, 400, |view, direction| {
+                if !safe_directions(view).is_empty() {
+                    let tile = view.next_tile(view.head().unwrap(), direction).unwrap();
+                    assert!(!view.is_blocked(tile), "seed {} stepped onto {:?}", seed, tile);
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn pathfinding_goes_through_a_wrapping_edge() {
+        let snake = (vec![[7, 1], [6, 1], [5, 1]], MovementDirection::Up);
+
+        let world = world(10, 10, snake.clone(), [5, 8]);
+        assert_eq!(PathfindingController.next_direction(&world.view(0)), MovementDirection::Left);
+
+        let mut world = self::world(10, 10, snake, [5, 8]);
+        world.boundary = BoundaryMode::Walls;
+        assert_eq!(PathfindingController.next_direction(&world.view(0)), MovementDirection::Right);
+    }
+
+    #[test]
+    fn greedy_sidesteps_a_blocked_direct_route() {
+        let mut world = World::with_players(10, 10, 2, 1);
+        world.snakes = vec![
+            Snake::new(vec![[5, 3], [5, 4], [5, 5]], MovementDirection::Right),
+            Snake::new(vec![[8, 6], [7, 6], [6, 6], [5, 6], [4, 6]], MovementDirection::Up),
+        ];
+        world.food = Some([5, 8]);
+
+        let direction = GreedyController.next_direction(&world.view(0));
+        assert_ne!(direction, MovementDirection::Right);
+        assert!(!world.view(0).is_blocked(world.next_tile([5, 5], direction).unwrap()));
+    }
+
+    #[test]
+    fn greedy_heads_straight_for_the_food() {
+        let world = world(10, 10, (vec![[5, 3], [5, 4], [5, 5]], MovementDirection::Right), [5, 8]);
+        assert_eq!(GreedyController.next_direction(&world.view(0)), MovementDirection::Right);
+    }
+
+    #[test]
+    fn hamiltonian_fills_a_small_even_board() {
+        let mut world = World::with_seed(4, 6, 3);
+        world.boundary = BoundaryMode::Walls;
+
+        play(&mut world, &mut HamiltonianController::new(), 10_000, |_, _| {});
+        assert!(world.snakes[0].is_alive());
+        assert_eq!(world.snakes[0].length(), 4 * 6);
+        assert_eq!(world.food, None);
+    }
+
+    #[test]
+    fn hamiltonian_falls_back_to_pathfinding_on_an_odd_board() {
+        let mut world = World::with_seed(5, 5, 3);
+        let mut hamiltonian = HamiltonianController::new();
+        play(&mut world, &mut PathfindingController, 200, |view, direction| {
+            assert_eq!(hamiltonian.next_direction(view), direction);
+        });
+    }
+
+    #[test]
+    fn hamiltonian_falls_back_when_another_snake_is_in_the_way() {
+        let mut world = World::with_players(6, 6, 2, 1);
+        world.snakes = vec![
+            Snake::new(vec![[0, 0], [0, 1], [0, 2]], MovementDirection::Right),
+            Snake::new(vec![[2, 3], [1, 3], [0, 3], [0, 4]], MovementDirection::Right),
+        ];
+        world.food = Some([5, 5]);
+
+        let view = world.view(0);
+        let direction = HamiltonianController::new().next_direction(&view);
+        assert_eq!(direction, PathfindingController.next_direction(&view));
+        assert!(!view.is_blocked(view.next_tile([0, 2], direction).unwrap()));
+
+        // With the way clear, it follows the cycle along the top row
+        world.snakes.truncate(1);
+        assert_eq!(HamiltonianController::new().next_direction(&world.view(0)), MovementDirection::Right);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::controller::Controller;
+use crate::world::World;
+
+/// Recorded as the death cause of games stopped by the tick limit rather than by dying
+pub const TICK_LIMIT_CAUSE: &str = "tick-limit";
+
+/// Recorded as the death cause of games with several players that player 1 won by outliving
+/// everyone else
+pub const SURVIVED_CAUSE: &str = "survived";
+
+/// The outcome of one headless game, from the point of view of player 1
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GameResult {
+    pub seed: u64,
+    pub food_eaten: u32,
+    pub length: usize,
+    pub ticks: u64,
+    pub death_cause: String,
+}
+
+/// Aggregate statistics over a batch of games
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchSummary {
+    pub games: usize,
+    pub mean_food_eaten: f64,
+    pub mean_length: f64,
+    pub mean_ticks: f64,
+    pub max_food_eaten: u32,
+    pub max_length: usize,
+    pub death_causes: BTreeMap<String, usize>,
+}
+
+impl BatchSummary {
+    pub fn from_results(results: &[GameResult]) -> BatchSummary {
+        let mean = |total: f64| if results.is_empty() { 0.0 } else { total / results.len() as f64 };
+
+        let mut death_causes = BTreeMap::new();
+        for result in results {
+            *death_causes.entry(result.death_cause.clone()).or_insert(0) += 1;
+        }
+
+        BatchSummary {
+            games: results.len(),
+            mean_food_eaten: mean(results.iter().map(|result| result.food_eaten as f64).sum()),
+            mean_length: mean(results.iter().map(|result| result.length as f64).sum()),
+            mean_ticks: mean(results.iter().map(|result| result.ticks as f64).sum()),
+            max_food_eaten: results.iter().map(|result| result.food_eaten).max().unwrap_or(0),
+            max_length: results.iter().map(|result| result.length).max().unwrap_or(0),
+            death_causes,
+        }
+    }
+}
+
+/// Plays one game with `seed` to the end, or until `max_ticks` have passed, as fast as possible.
+/// `controllers` steer the snakes in player order.
+pub fn play_game(config: &Config, seed: u64, controllers: &mut [Box<dyn Controller>], max_ticks: u64) -> GameResult {
+    let mut world = World::from_config(&Config { seed: Some(seed), ..config.clone() });
+    let mut controllers: Vec<&mut dyn Controller> = controllers.iter_mut().map(|controller| controller.as_mut() as &mut dyn Controller).collect();
+
+    while !world.is_game_over() && world.tick < max_ticks {
+        world.step_with(&mut controllers);
+    }
+
+    let snake = &world.snakes[0];
+    GameResult {
+        seed,
+        food_eaten: snake.score.food_eaten,
+        length: snake.length(),
+        ticks: snake.score.ticks,
+        death_cause: match snake.death_cause {
+            Some(cause) => cause.name().to_string(),
+            None if world.is_game_over() => SURVIVED_CAUSE.to_string(),
+            None => TICK_LIMIT_CAUSE.to_string(),
+        },
+    }
+}
+
+/// Plays `games` games with consecutive seeds starting at `first_seed`, spread over `threads`
+/// threads. Each snake in each game gets a fresh controller from `make_controller`, which is
+/// passed a seed derived from the game's seed and the player's index. Results are returned in
+/// seed order, so a batch is reproducible whatever the thread count.
+pub fn run_batch<F>(
+    config: &Config,
+    games: usize,
+    first_seed: u64,
+    max_ticks: u64,
+    threads: usize,
+    make_controller: F,
+) -> Vec<GameResult>
+    where F: Fn(u64) -> Box<dyn Controller> + Sync
+{
+    let next_game = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; games]);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let i_game = next_game.fetch_add(1, Ordering::Relaxed);
+                if i_game >= games {
+                    break;
+                }
+
+                let seed = first_seed.wrapping_add(i_game as u64);
+                let mut controllers: Vec<Box<dyn Controller>> = (0..config.players)
+                    .map(|i_player| make_controller(seed.wrapping_add(i_player as u64)))
+                    .collect();
+                let result = play_game(config, seed, &mut controllers, max_ticks);
+                results.lock().unwrap()[i_game] = Some(result);
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+/// Writes one CSV row per game, with a header row
+pub fn write_csv<W: Write>(results: &[GameResult], writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "seed,food_eaten,length,ticks,death_cause")?;
+    for result in results {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            result.seed, result.food_eaten, result.length, result.ticks, result.death_cause,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller;
+
+    #[test]
+    fn results_do_not_depend_on_the_thread_count() {
+        let config = Config { row_count: 8, col_count: 10, players: 2, ..Config::default() };
+        let run = |threads| {
+            run_batch(&config, 12, 40, 300, threads, |seed| controller::controller_by_name("random", seed).unwrap())
+        };
+
+        let single = run(1);
+        let several = run(4);
+        assert_eq!(single.iter().map(|result| result.seed).collect::<Vec<_>>(), (40..52).collect::<Vec<_>>());
+        assert_eq!(several, single);
+        assert_eq!(BatchSummary::from_results(&several), BatchSummary::from_results(&single));
+    }
+}
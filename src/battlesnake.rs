@@ -0,0 +1,439 @@
+//! The Battlesnake API, so that bots written for Battlesnake in any language can play in this
+//! engine. Battlesnake puts `y = 0` on the bottom row and lists bodies head first, while `World`
+//! counts rows from the top and stores bodies tail first; the types here translate between the two.
+//!
+//! Snakes in `World` never starve, so every snake is reported with full health. A snake that
+//! has died stays on the board as an obstacle, so it is still listed with the others. The walls
+//! of a map are sent as hazards that take all of a snake's health at once.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::map::Map;
+use crate::world::{BoundaryMode, MovementDirection, Snake, World, WorldView};
+
+/// The API version this engine speaks
+pub const API_VERSION: &str = "1";
+
+/// How long a bot gets to answer a move request, in milliseconds, unless configured otherwise
+pub const DEFAULT_TIMEOUT: u64 = 500;
+
+const FULL_HEALTH: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Coord {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Coord {
+    pub fn from_tile(tile: [usize; 2], row_count: usize) -> Coord {
+        Coord { x: tile[1], y: row_count - 1 - tile[0] }
+    }
+
+    /// The tile in a `World` with `row_count` rows, or `None` if the coordinate is off the board
+    pub fn to_tile(self, row_count: usize) -> Option<[usize; 2]> {
+        if self.y < row_count { Some([row_count - 1 - self.y, self.x]) } else { None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RulesetSettings {
+    pub food_spawn_chance: u32,
+    pub minimum_food: u32,
+    pub hazard_damage_per_turn: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ruleset {
+    /// `standard` when the edges are walls, `wrapped` when they wrap
+    pub name: String,
+    pub version: String,
+    #[serde(default = "default_settings")]
+    pub settings: RulesetSettings,
+}
+
+/// `World` always has exactly one food on the board and no hazards other than map walls
+fn default_settings() -> RulesetSettings {
+    RulesetSettings { food_spawn_chance: 0, minimum_food: 1, hazard_damage_per_turn: 0 }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Game {
+    pub id: String,
+    pub ruleset: Ruleset,
+    #[serde(default)]
+    pub map: String,
+    /// How long a bot gets to answer a move request, in milliseconds
+    pub timeout: u64,
+    #[serde(default)]
+    pub source: String,
+}
+
+impl Game {
+    /// Describes a game on a board with `boundary` edges. The walls of `map` are sent as hazards
+    /// that kill on contact. Battlesnake only knows boards that wrap on every edge or on none,
+    /// so boards that wrap on one axis only are an error.
+    pub fn new(id: String, boundary: BoundaryMode, map: Option<&Map>, timeout: u64) -> Result<Game, String> {
+        let name = match boundary {
+            BoundaryMode::Walls => "standard",
+            BoundaryMode::Wrap => "wrapped",
+            BoundaryMode::WrapHorizontal | BoundaryMode::WrapVertical => {
+                return Err(format!("Battlesnake has no ruleset for {} boundaries (expected wrap or walls)", boundary));
+            }
+        };
+        let mut settings = default_settings();
+        if map.is_some() {
+            settings.hazard_damage_per_turn = FULL_HEALTH;
+        }
+
+        Ok(Game {
+            id,
+            ruleset: Ruleset {
+                name: name.to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                settings,
+            },
+            map: map.map(|map| map.name.clone()).unwrap_or_else(|| String::from("standard")),
+            timeout,
+            source: String::from("custom"),
+        })
+    }
+
+    pub fn boundary(&self) -> BoundaryMode {
+        if self.ruleset.name == "wrapped" { BoundaryMode::Wrap } else { BoundaryMode::Walls }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Customizations {
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub head: String,
+    #[serde(default)]
+    pub tail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Battlesnake {
+    pub id: String,
+    pub name: String,
+    pub health: u32,
+    /// From the head to the tail
+    pub body: Vec<Coord>,
+    /// How long the bot took to answer the last move request, in milliseconds
+    #[serde(default)]
+    pub latency: String,
+    pub head: Coord,
+    pub length: usize,
+    #[serde(default)]
+    pub shout: String,
+    #[serde(default)]
+    pub squad: String,
+    #[serde(default)]
+    pub customizations: Customizations,
+}
+
+impl Battlesnake {
+    /// The snake with index `i` in a `World`
+    pub fn from_snake(i: usize, snake: &Snake, row_count: usize) -> Battlesnake {
+        let body: Vec<Coord> = snake.body.iter().rev().map(|tile| Coord::from_tile(*tile, row_count)).collect();
+
+        Battlesnake {
+            id: snake_id(i),
+            name: format!("Player {}", i + 1),
+            health: FULL_HEALTH,
+            head: body.first().copied().unwrap_or(Coord { x: 0, y: 0 }),
+            length: body.len(),
+            body,
+            latency: String::from("0"),
+            shout: String::new(),
+            squad: String::new(),
+            customizations: Customizations::default(),
+        }
+    }
+}
+
+/// The ID of the snake with index `i`
+pub fn snake_id(i: usize) -> String {
+    format!("snake-{}", i + 1)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Board {
+    pub height: usize,
+    pub width: usize,
+    pub food: Vec<Coord>,
+    #[serde(default)]
+    pub hazards: Vec<Coord>,
+    pub snakes: Vec<Battlesnake>,
+}
+
+/// The request body of `/start`, `/move` and `/end`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameState {
+    pub game: Game,
+    pub turn: u64,
+    pub board: Board,
+    pub you: Battlesnake,
+}
+
+impl GameState {
+    /// What the bot steering snake `view.you` is told about the board
+    pub fn from_view(game: &Game, view: &WorldView) -> GameState {
+        let snakes: Vec<Battlesnake> = view.snakes.iter()
+            .enumerate()
+            .map(|(i, snake)| Battlesnake::from_snake(i, snake, view.row_count))
+            .collect();
+
+        GameState {
+            game: game.clone(),
+            turn: view.tick,
+            board: Board {
+                height: view.row_count,
+                width: view.col_count,
+                food: view.food.iter().map(|tile| Coord::from_tile(*tile, view.row_count)).collect(),
+                hazards: view.map
+                    .map(|map| map.walls().into_iter().map(|tile| Coord::from_tile(tile, view.row_count)).collect())
+                    .unwrap_or_default(),
+                snakes: snakes.clone(),
+            },
+            you: snakes[view.you].clone(),
+        }
+    }
+
+    /// Rebuilds the board as a `World`, along with the index of the snake the request is for.
+    /// Headings are worked out from each snake's head and neck, and hazards become walls. The
+    /// world is only meant to be looked at, since food placement depends on the other engine's
+    /// random state.
+    pub fn to_world(&self) -> Result<(World, usize), String> {
+        let board = &self.board;
+        let tile = |coord: Coord| {
+            coord.to_tile(board.height)
+                .filter(|tile| tile[1] < board.width)
+                .ok_or_else(|| format!("({}, {}) is off the {}x{} board", coord.x, coord.y, board.width, board.height))
+        };
+
+        // Built field by field, since the board may be smaller than `World::with_players` allows
+        let mut world = World {
+            is_running: true,
+            row_count: board.height,
+            col_count: board.width,
+            boundary: self.game.boundary(),
+            seed: 0,
+            rng: ChaCha8Rng::seed_from_u64(0),
+            snakes: Vec::with_capacity(board.snakes.len()),
+            food: match board.food.first() {
+                Some(food) => Some(tile(*food)?),
+                None => None,
+            },
+            tick: self.turn,
+            map: None,
+        };
+
+        if !board.hazards.is_empty() {
+            let walls = board.hazards.iter().map(|coord| tile(*coord)).collect::<Result<Vec<_>, _>>()?;
+            world.map = Some(Map::with_walls(&self.game.map, board.height, board.width, &walls));
+        }
+
+        for battlesnake in board.snakes.iter() {
+            let body = battlesnake.body.iter().rev().map(|coord| tile(*coord)).collect::<Result<Vec<_>, _>>()?;
+            let heading = match body.len() {
+                0 | 1 => MovementDirection::Up,
+                len => {
+                    let (neck, head) = (body[len - 2], body[len - 1]);
+                    [MovementDirection::Up, MovementDirection::Left, MovementDirection::Down, MovementDirection::Right]
+                        .iter()
+                        .copied()
+                        .find(|direction| world.next_tile(neck, *direction) == Some(head))
+                        // Battlesnake stacks a new snake's segments on one tile
+                        .unwrap_or(MovementDirection::Up)
+                }
+            };
+            world.snakes.push(Snake::new(body, heading));
+        }
+
+        let you = board.snakes.iter()
+            .position(|snake| snake.id == self.you.id)
+            .ok_or_else(|| format!("snake {} is not on the board", self.you.id))?;
+        Ok((world, you))
+    }
+}
+
+/// The response body of `/move`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveResponse {
+    #[serde(rename = "move")]
+    pub direction: String,
+    #[serde(default)]
+    pub shout: String,
+}
+
+impl MoveResponse {
+    pub fn new(direction: MovementDirection) -> MoveResponse {
+        MoveResponse { direction: direction_name(direction).to_string(), shout: String::new() }
+    }
+
+    pub fn direction(&self) -> Option<MovementDirection> {
+        match self.direction.as_str() {
+            "up" => Some(MovementDirection::Up),
+            "left" => Some(MovementDirection::Left),
+            "down" => Some(MovementDirection::Down),
+            "right" => Some(MovementDirection::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Battlesnake's name for a direction. Up is towards the top row in both coordinate systems.
+pub fn direction_name(direction: MovementDirection) -> &'static str {
+    match direction {
+        MovementDirection::Up => "up",
+        MovementDirection::Left => "left",
+        MovementDirection::Down => "down",
+        MovementDirection::Right => "right",
+    }
+}
+
+/// The response body of `GET /`, describing the bot
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Info {
+    #[serde(default)]
+    pub apiversion: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub head: String,
+    #[serde(default)]
+    pub tail: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+/// Steers a snake by asking an external bot over HTTP. A bot that fails to answer in time, or
+/// answers with something other than a direction, keeps going straight, as in Battlesnake.
+#[cfg(feature = "battlesnake")]
+#[derive(Debug)]
+pub struct BattlesnakeController {
+    pub url: String,
+    pub game: Game,
+    /// How many move requests failed or timed out
+    pub failed_moves: u32,
+    /// Why the last request failed, if it did
+    pub last_error: Option<String>,
+    agent: ureq::Agent,
+    /// How long the bot took to answer the last move request, in milliseconds
+    latency: u128,
+}
+
+#[cfg(feature = "battlesnake")]
+impl BattlesnakeController {
+    pub fn new(url: &str, game: Game) -> BattlesnakeController {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(std::time::Duration::from_millis(game.timeout))
+            .build();
+
+        BattlesnakeController {
+            url: url.trim_end_matches('/').to_string(),
+            game,
+            failed_moves: 0,
+            last_error: None,
+            agent,
+            latency: 0,
+        }
+    }
+
+    /// Asks the bot to describe itself, which also checks that it is up
+    pub fn info(&self) -> Result<Info, String> {
+        let response = self.agent.get(&self.url).call().map_err(|error| error.to_string())?;
+        let body = response.into_string().map_err(|error| error.to_string())?;
+        serde_json::from_str(&body).map_err(|error| format!("invalid info from {}: {}", self.url, error))
+    }
+
+    /// Tells the bot a game is starting. Bots are not required to answer anything in particular.
+    pub fn start(&mut self, view: &WorldView) -> Result<(), String> {
+        self.post("start", view).map(|_| ())
+    }
+
+    /// Tells the bot the game is over, showing it the final board
+    pub fn end(&mut self, view: &WorldView) -> Result<(), String> {
+        self.post("end", view).map(|_| ())
+    }
+
+    fn post(&self, path: &str, view: &WorldView) -> Result<String, String> {
+        let mut state = GameState::from_view(&self.game, view);
+        state.you.latency = self.latency.to_string();
+        let body = serde_json::to_string(&state).map_err(|error| error.to_string())?;
+
+        self.agent.post(&format!("{}/{}", self.url, path))
+            .set("Content-Type", "application/json")
+            .send_string(&body)
+            .map_err(|error| error.to_string())?
+            .into_string()
+            .map_err(|error| error.to_string())
+    }
+}
+
+#[cfg(feature = "battlesnake")]
+impl crate::controller::Controller for BattlesnakeController {
+    fn next_direction(&mut self, view: &WorldView) -> MovementDirection {
+        let started_at = std::time::Instant::now();
+        let result = self.post("move", view).and_then(|body| {
+            let response: MoveResponse = serde_json::from_str(&body).map_err(|error| error.to_string())?;
+            response.direction().ok_or_else(|| format!("unknown move: {}", response.direction))
+        });
+        self.latency = started_at.elapsed().as_millis();
+
+        match result {
+            Ok(direction) => direction,
+            Err(error) => {
+                self.failed_moves += 1;
+                self.last_error = Some(error);
+                view.heading
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rulesets_match_the_boundary() {
+        for boundary in [BoundaryMode::Walls, BoundaryMode::Wrap].iter() {
+            let game = Game::new(String::from("game"), *boundary, None, DEFAULT_TIMEOUT).unwrap();
+            assert_eq!(game.boundary(), *boundary);
+        }
+
+        for boundary in [BoundaryMode::WrapHorizontal, BoundaryMode::WrapVertical].iter() {
+            assert!(Game::new(String::from("game"), *boundary, None, DEFAULT_TIMEOUT).is_err());
+        }
+    }
+
+    #[test]
+    fn game_state_round_trips_through_a_world() {
+        let mut world = World::with_players(11, 11, 2, 5);
+        world.boundary = BoundaryMode::Walls;
+        world.step();
+        world.step();
+
+        let game = Game::new(String::from("game"), world.boundary, None, DEFAULT_TIMEOUT).unwrap();
+        let state = GameState::from_view(&game, &world.view(1));
+        let (rebuilt, you) = state.to_world().unwrap();
+
+        assert_eq!(you, 1);
+        assert_eq!(rebuilt.boundary, world.boundary);
+        assert_eq!(rebuilt.food, world.food);
+        assert_eq!(rebuilt.tick, world.tick);
+        for (rebuilt, snake) in rebuilt.snakes.iter().zip(world.snakes.iter()) {
+            assert_eq!(rebuilt.body, snake.body);
+            assert_eq!(rebuilt.movement_direction, snake.movement_direction);
+        }
+    }
+}
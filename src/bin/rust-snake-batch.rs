@@ -0,0 +1,123 @@
+//! Plays many games headlessly with a built-in controller, as fast as the machine allows,
+//! and reports statistics about them. Takes the same game options as the other front-ends.
+
+use std::env;
+use std::io;
+use std::process;
+use std::thread;
+
+use rand::Rng;
+
+use rust_snake::batch::{self, BatchSummary};
+use rust_snake::config::parse_value;
+use rust_snake::controller;
+use rust_snake::Config;
+
+const DEFAULT_GAMES: usize = 100;
+const DEFAULT_AI: &str = "random";
+const DEFAULT_MAX_TICKS: u64 = 100_000;
+
+const USAGE: &str = "\
+usage: rust-snake-batch [batch options] [game options]
+
+batch options:
+    --games <number>       how many games to play (default 100)
+    --max-ticks <number>   stop a game that lasts this long (default 100000)
+    --threads <number>     how many games to play at once (default: one per CPU)
+    --format <format>      json for the summary and every game, csv for one row per game
+
+Games are played by the bot chosen with --ai, or by the random bot if none is given.
+They use consecutive seeds starting at --seed, or at a random seed if none is given.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug)]
+struct BatchOptions {
+    games: usize,
+    max_ticks: u64,
+    threads: usize,
+    format: OutputFormat,
+}
+
+impl BatchOptions {
+    /// Takes the batch options out of `args`, leaving the game options for `Config::from_args`
+    fn from_args(args: Vec<String>) -> Result<(BatchOptions, Vec<String>), String> {
+        let mut options = BatchOptions {
+            games: DEFAULT_GAMES,
+            max_ticks: DEFAULT_MAX_TICKS,
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            format: OutputFormat::Json,
+        };
+        let mut game_args = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--games" => options.games = parse_value(&arg, args.next())?,
+                "--max-ticks" => options.max_ticks = parse_value(&arg, args.next())?,
+                "--threads" => options.threads = parse_value(&arg, args.next())?,
+                "--format" => {
+                    options.format = match parse_value::<String>(&arg, args.next())?.as_str() {
+                        "json" => OutputFormat::Json,
+                        "csv" => OutputFormat::Csv,
+                        format => return Err(format!("unknown format: {} (expected json or csv)", format)),
+                    }
+                }
+                _ => game_args.push(arg),
+            }
+        }
+
+        Ok((options, game_args))
+    }
+}
+
+fn main() {
+    let (options, config) = BatchOptions::from_args(env::args().skip(1).collect())
+        .and_then(|(options, game_args)| Ok((options, Config::from_args(game_args.into_iter())?)))
+        .unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            eprintln!("{}", USAGE);
+            eprintln!();
+            eprintln!("{}", rust_snake::config::USAGE);
+            process::exit(2);
+        });
+
+    let ai = config.ai.clone().unwrap_or_else(|| String::from(DEFAULT_AI));
+    let first_seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let results = batch::run_batch(
+        &config,
+        options.games,
+        first_seed,
+        options.max_ticks,
+        options.threads,
+        |seed| controller::controller_by_name(&ai, seed).unwrap(),
+    );
+    let summary = BatchSummary::from_results(&results);
+
+    let output = match options.format {
+        OutputFormat::Json => {
+            let report = serde_json::json!({
+                "ai": ai,
+                "first_seed": first_seed,
+                "summary": summary,
+                "games": results,
+            });
+            serde_json::to_writer_pretty(io::stdout(), &report).map_err(io::Error::from)
+        }
+        OutputFormat::Csv => batch::write_csv(&results, &mut io::stdout()),
+    };
+
+    if let Err(error) = output {
+        eprintln!("Could not write results: {}", error);
+        process::exit(1);
+    }
+
+    eprintln!(
+        "{} games with {} from seed {}: mean food {:.2}, mean length {:.2}, mean ticks {:.1}",
+        summary.games, ai, first_seed, summary.mean_food_eaten, summary.mean_length, summary.mean_ticks,
+    );
+}
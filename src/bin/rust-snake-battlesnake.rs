@@ -0,0 +1,156 @@
+//! Plays games between external bots that speak the Battlesnake HTTP API, with this engine's
+//! rules deciding the outcome. Takes the same game options as the other front-ends.
+
+use std::env;
+use std::process;
+
+use rand::Rng;
+
+use rust_snake::battlesnake::{self, BattlesnakeController, Game};
+use rust_snake::config::parse_value;
+use rust_snake::{Config, Controller, World};
+
+const DEFAULT_GAMES: usize = 1;
+const DEFAULT_MAX_TICKS: u64 = 10_000;
+
+const USAGE: &str = "\
+usage: rust-snake-battlesnake --bot <url> [--bot <url> ...] [options] [game options]
+
+options:
+    --bot <url>            base URL of a Battlesnake bot; give one per snake, in player order
+    --games <number>       how many games to play (default 1)
+    --timeout <ms>         how long a bot gets to answer each move (default 500)
+    --max-ticks <number>   stop a game that lasts this long (default 10000)
+
+Bots that are slow or fail to answer keep going straight. Games use consecutive seeds
+starting at --seed, or at a random seed if none is given.";
+
+#[derive(Debug)]
+struct MatchOptions {
+    bots: Vec<String>,
+    games: usize,
+    timeout: u64,
+    max_ticks: u64,
+}
+
+impl MatchOptions {
+    /// Takes the match options out of `args`, leaving the game options for `Config::from_args`
+    fn from_args(args: Vec<String>) -> Result<(MatchOptions, Vec<String>), String> {
+        let mut options = MatchOptions {
+            bots: Vec::new(),
+            games: DEFAULT_GAMES,
+            timeout: battlesnake::DEFAULT_TIMEOUT,
+            max_ticks: DEFAULT_MAX_TICKS,
+        };
+        let mut game_args = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bot" => options.bots.push(parse_value(&arg, args.next())?),
+                "--games" => options.games = parse_value(&arg, args.next())?,
+                "--timeout" => options.timeout = parse_value(&arg, args.next())?,
+                "--max-ticks" => options.max_ticks = parse_value(&arg, args.next())?,
+                _ => game_args.push(arg),
+            }
+        }
+
+        if options.bots.is_empty() {
+            return Err(String::from("at least one --bot is required"));
+        }
+
+        Ok((options, game_args))
+    }
+}
+
+fn main() {
+    let (options, config, game) = MatchOptions::from_args(env::args().skip(1).collect())
+        .and_then(|(options, game_args)| {
+            let mut config = Config::from_args(game_args.into_iter())?;
+            config.players = options.bots.len();
+            config.validate()?;
+            let game = Game::new(String::new(), config.boundary, config.map.as_ref(), options.timeout)?;
+            Ok((options, config, game))
+        })
+        .unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            eprintln!("{}", USAGE);
+            eprintln!();
+            eprintln!("{}", rust_snake::config::USAGE);
+            process::exit(2);
+        });
+
+    // Check every bot is up before starting, rather than forfeiting its first game
+    for url in options.bots.iter() {
+        let probe = BattlesnakeController::new(url, game.clone());
+        match probe.info() {
+            Ok(info) => eprintln!("{}: API version {}, by {}", url, info.apiversion, info.author),
+            Err(error) => {
+                eprintln!("Could not reach the bot at {}: {}", url, error);
+                process::exit(1);
+            }
+        }
+    }
+
+    let first_seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut wins = vec![0; options.bots.len()];
+
+    for i_game in 0..options.games {
+        let seed = first_seed.wrapping_add(i_game as u64);
+        let mut world = World::from_config(&Config { seed: Some(seed), ..config.clone() });
+        let game = Game { id: format!("rust-snake-{}", seed), ..game.clone() };
+        let mut controllers: Vec<BattlesnakeController> = options.bots.iter()
+            .map(|url| BattlesnakeController::new(url, game.clone()))
+            .collect();
+
+        for (i, controller) in controllers.iter_mut().enumerate() {
+            if let Err(error) = controller.start(&world.view(i)) {
+                eprintln!("{} did not accept the start of game {}: {}", controller.url, game.id, error);
+            }
+        }
+
+        while !world.is_game_over() && world.tick < options.max_ticks {
+            let mut steering: Vec<&mut dyn Controller> = controllers.iter_mut()
+                .map(|controller| controller as &mut dyn Controller)
+                .collect();
+            world.step_with(&mut steering);
+        }
+
+        for (i, controller) in controllers.iter_mut().enumerate() {
+            if let Err(error) = controller.end(&world.view(i)) {
+                eprintln!("{} did not accept the end of game {}: {}", controller.url, game.id, error);
+            }
+        }
+
+        let outcome = match world.winner() {
+            Some(winner) => {
+                wins[winner] += 1;
+                format!("won by {} ({})", battlesnake::snake_id(winner), options.bots[winner])
+            }
+            None if !world.is_game_over() => String::from("stopped by the tick limit"),
+            None if world.snakes.len() == 1 => String::from("over"),
+            None => String::from("a draw"),
+        };
+        println!("Game {} (seed {}): {} after {} turns", i_game + 1, seed, outcome, world.tick);
+
+        for (i, (snake, controller)) in world.snakes.iter().zip(controllers.iter()).enumerate() {
+            println!(
+                "    {} {}: length {}, food {}, {}, {} failed moves{}",
+                battlesnake::snake_id(i),
+                controller.url,
+                snake.length(),
+                snake.score.food_eaten,
+                snake.death_cause.map(|cause| format!("died: {}", cause)).unwrap_or_else(|| String::from("alive")),
+                controller.failed_moves,
+                controller.last_error.as_ref().map(|error| format!(" (last: {})", error)).unwrap_or_default(),
+            );
+        }
+    }
+
+    if options.bots.len() > 1 {
+        println!();
+        for (i, url) in options.bots.iter().enumerate() {
+            println!("{} {}: {} of {} games won", battlesnake::snake_id(i), url, wins[i], options.games);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! A headless client for `rust-snake-server` that plays with a built-in bot. Useful for filling
+//! empty seats, load testing, and trying the server out on one machine.
+
+use std::env;
+use std::process;
+
+use rust_snake::config::parse_value;
+use rust_snake::controller;
+use rust_snake::net::{ClientMessage, Connection, ServerMessage, DEFAULT_SERVER_ADDRESS};
+
+const DEFAULT_AI: &str = "bfs";
+
+const USAGE: &str = "\
+usage: rust-snake-client [options]
+
+options:
+    --connect <address>    server to join (default 127.0.0.1:7878)
+    --name <name>          name shown to the other players (default: the bot's name)
+    --ai <name>            bot to play with: random, greedy, bfs or hamiltonian (default bfs)";
+
+#[derive(Debug)]
+struct ClientOptions {
+    address: String,
+    name: Option<String>,
+    ai: String,
+}
+
+impl ClientOptions {
+    fn from_args<I: Iterator<Item=String>>(mut args: I) -> Result<ClientOptions, String> {
+        let mut options = ClientOptions {
+            address: String::from(DEFAULT_SERVER_ADDRESS),
+            name: None,
+            ai: String::from(DEFAULT_AI),
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--connect" => options.address = parse_value(&arg, args.next())?,
+                "--name" => options.name = Some(parse_value(&arg, args.next())?),
+                "--ai" => options.ai = parse_value(&arg, args.next())?,
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+
+        if !controller::CONTROLLER_NAMES.contains(&options.ai.as_str()) {
+            return Err(format!("unknown AI: {} (expected one of {})", options.ai, controller::CONTROLLER_NAMES.join(", ")));
+        }
+
+        Ok(options)
+    }
+}
+
+fn main() {
+    let ClientOptions { address, name, ai } = ClientOptions::from_args(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+
+    let name = name.unwrap_or_else(|| ai.clone());
+    let mut connection = Connection::join(&address, &name).unwrap_or_else(|error| {
+        eprintln!("Could not join {}: {}", address, error);
+        process::exit(1);
+    });
+    eprintln!("Joined {} as player {}", address, connection.player + 1);
+
+    let mut bot = controller::controller_by_name(&ai, connection.player as u64).unwrap();
+    let mut finished_round = 0;
+
+    loop {
+        let snapshot = match connection.receive() {
+            Ok(Some(ServerMessage::Snapshot(snapshot))) => snapshot,
+            Ok(Some(_)) => continue,
+            Ok(None) => break,
+            Err(error) => {
+                eprintln!("Lost connection to {}: {}", address, error);
+                process::exit(1);
+            }
+        };
+
+        let world = snapshot.to_world(connection.config.map.as_ref());
+        if world.is_game_over() {
+            if finished_round != snapshot.round {
+                finished_round = snapshot.round;
+                let snake = &world.snakes[connection.player];
+                println!(
+                    "Round {}: {}, food {}, length {}, ticks {}",
+                    snapshot.round,
+                    match world.winner() {
+                        Some(winner) if winner == connection.player => String::from("won"),
+                        Some(winner) => format!("player {} won", winner + 1),
+                        None => snake.death_cause.map(|cause| cause.to_string()).unwrap_or_else(|| String::from("draw")),
+                    },
+                    snake.score.food_eaten,
+                    snake.length(),
+                    snake.score.ticks,
+                );
+            }
+            continue;
+        }
+
+        let view = snapshot.view(connection.player, connection.config.map.as_ref());
+        if !snapshot.is_running || !view.snakes[connection.player].is_alive() {
+            continue;
+        }
+
+        let direction = bot.next_direction(&view);
+        if direction != view.heading {
+            let input = ClientMessage::Input { direction, tick: snapshot.tick };
+            if connection.send(&input).is_err() {
+                break;
+            }
+        }
+    }
+}
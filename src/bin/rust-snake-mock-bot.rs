@@ -0,0 +1,203 @@
+//! A stand-in for an external Battlesnake bot, serving the Battlesnake HTTP API with one of the
+//! built-in controllers. Handy for trying out `rust-snake-battlesnake` without writing a bot.
+
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use rust_snake::battlesnake::{GameState, Info, MoveResponse, API_VERSION};
+use rust_snake::config::parse_value;
+use rust_snake::controller;
+use rust_snake::Controller;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8000";
+const DEFAULT_AI: &str = "bfs";
+
+/// Requests longer than this, headers included, are refused rather than read into memory.
+/// A game state on the largest boards fits with plenty to spare.
+const MAX_REQUEST_LEN: u64 = 1 << 20;
+
+/// How long a client may take to send its request, so that a silent one cannot hold up the
+/// bot, which answers one request at a time
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+const USAGE: &str = "\
+usage: rust-snake-mock-bot [options]
+
+options:
+    --bind <address>       address to listen on (default 127.0.0.1:8000)
+    --ai <name>            bot that picks the moves: random, greedy, bfs or hamiltonian (default bfs)
+    --delay <ms>           wait this long before answering each move, to try out timeouts";
+
+#[derive(Debug)]
+struct MockOptions {
+    address: String,
+    ai: String,
+    delay: Duration,
+}
+
+impl MockOptions {
+    fn from_args<I: Iterator<Item=String>>(mut args: I) -> Result<MockOptions, String> {
+        let mut options = MockOptions {
+            address: String::from(DEFAULT_ADDRESS),
+            ai: String::from(DEFAULT_AI),
+            delay: Duration::from_secs(0),
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bind" => options.address = parse_value(&arg, args.next())?,
+                "--ai" => options.ai = parse_value(&arg, args.next())?,
+                "--delay" => options.delay = Duration::from_millis(parse_value(&arg, args.next())?),
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+
+        if !controller::CONTROLLER_NAMES.contains(&options.ai.as_str()) {
+            return Err(format!("unknown AI: {} (expected one of {})", options.ai, controller::CONTROLLER_NAMES.join(", ")));
+        }
+
+        Ok(options)
+    }
+}
+
+/// A parsed HTTP request; only what the Battlesnake API needs
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+fn main() {
+    let options = MockOptions::from_args(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+
+    let listener = TcpListener::bind(&options.address).unwrap_or_else(|error| {
+        eprintln!("Could not listen on {}: {}", options.address, error);
+        process::exit(1);
+    });
+    // The bound address, which has the real port when the given one was 0
+    let address = listener.local_addr().map(|address| address.to_string()).unwrap_or_else(|_| options.address.clone());
+    eprintln!("Serving the {} bot on http://{}", options.ai, address);
+
+    // One controller per snake per game, since some bots remember things between moves
+    let mut bots: HashMap<(String, String), Box<dyn Controller>> = HashMap::new();
+
+    for stream in listener.incoming() {
+        let result = stream.and_then(|mut stream| {
+            let request = read_request(&mut stream)?;
+            let (status, body) = respond(&request, &options, &mut bots);
+            write_response(&mut stream, status, &body)
+        });
+
+        if let Err(error) = result {
+            eprintln!("Request failed: {}", error);
+        }
+    }
+}
+
+fn respond(
+    request: &Request,
+    options: &MockOptions,
+    bots: &mut HashMap<(String, String), Box<dyn Controller>>,
+) -> (&'static str, String) {
+    if request.method == "GET" && request.path == "/" {
+        let info = Info {
+            apiversion: API_VERSION.to_string(),
+            author: String::from("rust-snake"),
+            color: String::from("#c80000"),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Info::default()
+        };
+        return ("200 OK", serde_json::to_string(&info).unwrap());
+    }
+
+    if request.method != "POST" {
+        return ("404 Not Found", String::from("{}"));
+    }
+
+    let state: GameState = match serde_json::from_str(&request.body) {
+        Ok(state) => state,
+        Err(error) => return ("400 Bad Request", serde_json::json!({ "error": error.to_string() }).to_string()),
+    };
+    let key = (state.game.id.clone(), state.you.id.clone());
+
+    match request.path.as_str() {
+        "/start" => {
+            bots.insert(key, controller::controller_by_name(&options.ai, 0).unwrap());
+            ("200 OK", String::from("{}"))
+        }
+        "/move" => {
+            let (world, you) = match state.to_world() {
+                Ok(world) => world,
+                Err(error) => return ("400 Bad Request", serde_json::json!({ "error": error }).to_string()),
+            };
+
+            thread::sleep(options.delay);
+            let bot = bots.entry(key).or_insert_with(|| controller::controller_by_name(&options.ai, 0).unwrap());
+            let direction = bot.next_direction(&world.view(you));
+            ("200 OK", serde_json::to_string(&MoveResponse::new(direction)).unwrap())
+        }
+        "/end" => {
+            bots.remove(&key);
+            ("200 OK", String::from("{}"))
+        }
+        _ => ("404 Not Found", String::from("{}")),
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> io::Result<Request> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_LEN));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    if content_length > MAX_REQUEST_LEN as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("request body of {} bytes is too long", content_length)));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request {
+        method,
+        path,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body,
+    )?;
+    stream.flush()
+}
@@ -0,0 +1,69 @@
+//! Hosts networked games: runs the authoritative world and lets clients join over TCP.
+//! Takes the same game options as the other front-ends; `--players` sets the number of slots.
+
+use std::env;
+use std::net::TcpListener;
+use std::process;
+use std::time::Duration;
+
+use rust_snake::config::parse_value;
+use rust_snake::net::DEFAULT_SERVER_ADDRESS;
+use rust_snake::server::{self, ServerOptions};
+use rust_snake::Config;
+
+const USAGE: &str = "\
+usage: rust-snake-server [server options] [game options]
+
+server options:
+    --bind <address>        address to listen on (default 127.0.0.1:7878); use 0.0.0.0:7878
+                            to accept players from other machines
+    --rounds <number>       stop after this many games (default: keep playing)
+    --round-break <seconds> pause between games (default 3)
+
+The first game starts once --players clients have joined. A bot, chosen with --ai, plays
+for anyone who leaves until someone else joins in their place.";
+
+fn main() {
+    let mut address = String::from(DEFAULT_SERVER_ADDRESS);
+    let mut options = ServerOptions::default();
+
+    let config = parse_args(env::args().skip(1).collect(), &mut address, &mut options).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("{}", USAGE);
+        eprintln!();
+        eprintln!("{}", rust_snake::config::USAGE);
+        process::exit(2);
+    });
+
+    let listener = TcpListener::bind(&address).unwrap_or_else(|error| {
+        eprintln!("Could not listen on {}: {}", address, error);
+        process::exit(1);
+    });
+
+    eprintln!("Waiting for {} players on {}", config.players, address);
+    if let Err(error) = server::serve(listener, &config, &options) {
+        eprintln!("Server error: {}", error);
+        process::exit(1);
+    }
+}
+
+/// Takes the server options out of `args` and builds the game configuration from the rest
+fn parse_args(args: Vec<String>, address: &mut String, options: &mut ServerOptions) -> Result<Config, String> {
+    let mut game_args = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => *address = parse_value(&arg, args.next())?,
+            "--rounds" => options.rounds = Some(parse_value(&arg, args.next())?),
+            "--round-break" => {
+                let seconds: f64 = parse_value(&arg, args.next())?;
+                options.round_break = Duration::try_from_secs_f64(seconds)
+                    .map_err(|_| format!("invalid value for {}: {}", arg, seconds))?;
+            }
+            _ => game_args.push(arg),
+        }
+    }
+
+    Config::from_args(game_args.into_iter())
+}
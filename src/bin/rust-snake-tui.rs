@@ -0,0 +1,451 @@
+//! Terminal front-end for Rust Snake, for playing over SSH or anywhere else without a display.
+//! It plays by the same rules as the window front-end and shares its options and high scores.
+
+use std::env;
+use std::io::{self, Stdout, Write};
+use std::process;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::{cursor, execute, queue, terminal};
+
+use rust_snake::config::{parse_value, MAX_PLAYERS};
+use rust_snake::controller::{self, DEFAULT_BOT};
+use rust_snake::net::{self, ClientMessage, Connection, ServerMessage, Snapshot};
+use rust_snake::{Config, Controller, FixedTimestep, HighScore, HighScoreTable, KeyboardController, MovementDirection, World};
+
+/// Each tile is two characters wide so that tiles look roughly square in a terminal
+const TILE_WIDTH: usize = 2;
+const TILE_EMPTY: &str = "  ";
+const TILE_SNAKE: &str = "██";
+const TILE_FOOD: &str = "██";
+const TILE_OBSTACLE: &str = "▓▓";
+
+/// One colour per player, in player order
+const COLOR_SNAKES: [Color; MAX_PLAYERS] = [Color::Red, Color::Blue, Color::Yellow, Color::Magenta];
+const COLOR_DEAD_SNAKE: Color = Color::DarkGrey;
+const COLOR_FOOD: Color = Color::Green;
+const COLOR_OBSTACLE: Color = Color::Grey;
+
+/// The direction keys of each player who steers from the keyboard, in player order
+const PLAYER_KEYS: [[(KeyCode, MovementDirection); 4]; 2] = [
+    [
+        (KeyCode::Up, MovementDirection::Up),
+        (KeyCode::Left, MovementDirection::Left),
+        (KeyCode::Down, MovementDirection::Down),
+        (KeyCode::Right, MovementDirection::Right),
+    ],
+    [
+        (KeyCode::Char('w'), MovementDirection::Up),
+        (KeyCode::Char('a'), MovementDirection::Left),
+        (KeyCode::Char('s'), MovementDirection::Down),
+        (KeyCode::Char('d'), MovementDirection::Right),
+    ],
+];
+
+/// How long to wait for a key press before checking whether a tick is due
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How much the + and - keys speed up or slow down the game, as a factor
+const SPEED_MULTIPLIER_STEP: f64 = 1.25;
+/// How far the + and - keys can go, so that a few presses always undo a run of presses the other way
+const MIN_SPEED_MULTIPLIER: f64 = 1.0 / 16.0;
+const MAX_SPEED_MULTIPLIER: f64 = 16.0;
+
+/// Switches the terminal to raw mode on the alternate screen, and back again when dropped
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter(stdout: &mut Stdout) -> io::Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+const REMOTE_USAGE: &str = "\
+To join a game hosted with rust-snake-server instead of playing locally:
+    --connect <address>    server address, such as 127.0.0.1:7878
+    --name <name>          name shown to the other players (default: your user name)
+The server decides the game settings, so other options cannot be given with --connect.";
+
+fn main() {
+    let (remote, game_args) = RemoteOptions::from_args(env::args().skip(1).collect()).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("{}", REMOTE_USAGE);
+        process::exit(2);
+    });
+
+    if let Some(remote) = remote {
+        if let Err(error) = run_remote(&remote) {
+            eprintln!("Could not play on {}: {}", remote.address, error);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let config = Config::from_args(game_args.into_iter()).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("{}", rust_snake::config::USAGE);
+        eprintln!();
+        eprintln!("{}", REMOTE_USAGE);
+        process::exit(2);
+    });
+
+    match run(&config) {
+        Ok(world) => eprintln!("Seed of the last game: {}", world.seed),
+        Err(error) => {
+            eprintln!("Terminal error: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RemoteOptions {
+    address: String,
+    name: String,
+}
+
+impl RemoteOptions {
+    /// Takes `--connect` and `--name` out of `args`, leaving the game options for `Config::from_args`
+    fn from_args(args: Vec<String>) -> Result<(Option<RemoteOptions>, Vec<String>), String> {
+        let mut address = None;
+        let mut name = env::var("USER").unwrap_or_else(|_| String::from("player"));
+        let mut game_args = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--connect" => address = Some(parse_value(&arg, args.next())?),
+                "--name" => name = parse_value(&arg, args.next())?,
+                _ => game_args.push(arg),
+            }
+        }
+
+        match address {
+            Some(_) if !game_args.is_empty() => Err(format!("{} cannot be combined with --connect", game_args[0])),
+            Some(address) => Ok((Some(RemoteOptions { address, name }), game_args)),
+            None => Ok((None, game_args)),
+        }
+    }
+}
+
+/// Plays until the player quits, returning the last world so its seed can be reported
+fn run(config: &Config) -> io::Result<World> {
+    // An unreadable high-score file is left alone, rather than overwritten with this session's
+    // scores. The error is printed before the terminal switches screens, so it stays visible.
+    let (mut high_scores, high_score_path) = HighScoreTable::load_default().unwrap_or_else(|error| {
+        eprintln!("{}; high scores will not be saved this session", error);
+        (HighScoreTable::default(), None)
+    });
+    // Shown under the high scores, since errors cannot be printed without corrupting the screen
+    let mut high_score_error = None;
+
+    let mut stdout = io::stdout();
+    let _guard = TerminalGuard::enter(&mut stdout)?;
+
+    let mut world = World::from_config(config);
+    // Each player steers with their own keys, unless a bot plays for them
+    let mut keyboards = vec![KeyboardController::new(); config.players];
+    let mut bots: Vec<Option<Box<dyn Controller>>> = (0..config.players)
+        .map(|i_player| {
+            let name = config.ai.as_deref().or(if i_player < PLAYER_KEYS.len() { None } else { Some(DEFAULT_BOT) });
+            name.map(|name| controller::controller_by_name(name, world.seed.wrapping_add(i_player as u64)).unwrap())
+        })
+        .collect();
+
+    let mode_key = HighScoreTable::mode_key(config);
+
+    // The rank of the finished game in the high-score table, once it has been recorded
+    let mut recorded_rank: Option<Option<usize>> = None;
+
+    let mut speed_multiplier = 1.0;
+    let mut level = config.speed_curve().level(world.max_food_eaten());
+    let mut timestep = FixedTimestep::new(config.tick_rate(level));
+    let mut needs_redraw = true;
+
+    loop {
+        if needs_redraw {
+            let high_score_table = recorded_rank.map(|rank| HighScoreList {
+                mode_key: &mode_key,
+                scores: high_scores.scores(&mode_key),
+                rank,
+                save_error: high_score_error.as_deref(),
+            });
+            let help = if world.snakes.len() > 1 {
+                "Arrows/WASD: turn  Space: pause  R: restart  +/-: speed  Q: quit"
+            } else {
+                "Arrows: turn  Space: pause  R: restart  +/-: speed  Q: quit"
+            };
+            draw(&mut stdout, &world, level, timestep.tick_rate(), help, high_score_table)?;
+            needs_redraw = false;
+        }
+
+        if event::poll(INPUT_POLL_INTERVAL)? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    if is_quit(&key) {
+                        return Ok(world);
+                    }
+
+                    for (keyboard, keys) in keyboards.iter_mut().zip(PLAYER_KEYS.iter()) {
+                        for (code, direction) in keys.iter() {
+                            if key.code == *code {
+                                keyboard.press(*direction);
+                            }
+                        }
+                    }
+
+                    match key.code {
+                        KeyCode::Char(' ') => world.is_running = !world.is_running,
+                        KeyCode::Char('r') | KeyCode::Char('R') => {
+                            world.restart();
+                            keyboards.iter_mut().for_each(KeyboardController::clear);
+                            recorded_rank = None;
+                        }
+                        KeyCode::Char('+') | KeyCode::Char('=') => {
+                            speed_multiplier = (speed_multiplier * SPEED_MULTIPLIER_STEP).min(MAX_SPEED_MULTIPLIER);
+                        }
+                        KeyCode::Char('-') => {
+                            speed_multiplier = (speed_multiplier / SPEED_MULTIPLIER_STEP).max(MIN_SPEED_MULTIPLIER);
+                        }
+                        _ => {}
+                    }
+                    needs_redraw = true;
+                }
+                Event::Resize(_, _) => {
+                    queue!(stdout, terminal::Clear(terminal::ClearType::All))?;
+                    needs_redraw = true;
+                }
+                _ => {}
+            }
+        }
+
+        level = config.speed_curve().level(world.max_food_eaten());
+        timestep.set_tick_rate(config.scaled_tick_rate(level, speed_multiplier));
+
+        for _ in 0..timestep.update(Instant::now()) {
+            let mut controllers: Vec<&mut dyn Controller> = keyboards.iter_mut()
+                .zip(bots.iter_mut())
+                .map(|(keyboard, bot)| match bot {
+                    Some(bot) => bot.as_mut() as &mut dyn Controller,
+                    None => keyboard as &mut dyn Controller,
+                })
+                .collect();
+            world.step_with(&mut controllers);
+            needs_redraw = true;
+        }
+
+        if world.is_game_over() && recorded_rank.is_none() {
+            // In a match only the winner's score counts
+            let scorer = if world.snakes.len() > 1 { world.winner() } else { Some(0) };
+            let rank = scorer.and_then(|i| high_scores.insert(&mode_key, HighScore::from_snake(&world.snakes[i])));
+            if let Some(path) = &high_score_path {
+                high_score_error = high_scores.save(path)
+                    .err()
+                    .map(|error| format!("Could not save high scores to {}: {}", path.display(), error));
+            }
+            recorded_rank = Some(rank);
+            needs_redraw = true;
+        }
+    }
+}
+
+/// Plays on a server until the player quits or the server closes the connection. The server
+/// runs the game; this only sends the player's turns and draws the snapshots it gets back.
+fn run_remote(remote: &RemoteOptions) -> io::Result<()> {
+    let mut connection = Connection::join(&remote.address, &remote.name)?;
+    let player = connection.player;
+    let config = connection.config.clone();
+    let mut sender = connection.sender()?;
+
+    // Snapshots are read on their own thread so that waiting for one never delays a key press
+    let (snapshots, snapshot_receiver) = mpsc::channel();
+    thread::spawn(move || {
+        while let Ok(Some(message)) = connection.receive() {
+            if let ServerMessage::Snapshot(snapshot) = message {
+                if snapshots.send(snapshot).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    let mut stdout = io::stdout();
+    let _guard = TerminalGuard::enter(&mut stdout)?;
+
+    let help = format!("You are player {}  Arrows/WASD: turn  Q: leave", player + 1);
+    let mut snapshot: Option<Snapshot> = None;
+    let mut needs_redraw = false;
+
+    loop {
+        if event::poll(INPUT_POLL_INTERVAL)? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    if is_quit(&key) {
+                        let _ = net::write_message(&mut sender, &ClientMessage::Leave);
+                        return Ok(());
+                    }
+
+                    // Either set of keys steers the player's own snake
+                    let direction = PLAYER_KEYS.iter()
+                        .flat_map(|keys| keys.iter())
+                        .find(|(code, _)| *code == key.code)
+                        .map(|(_, direction)| *direction);
+                    if let (Some(direction), Some(snapshot)) = (direction, &snapshot) {
+                        net::write_message(&mut sender, &ClientMessage::Input { direction, tick: snapshot.tick })?;
+                    }
+                }
+                Event::Resize(_, _) => {
+                    queue!(stdout, terminal::Clear(terminal::ClearType::All))?;
+                    needs_redraw = true;
+                }
+                _ => {}
+            }
+        }
+
+        loop {
+            match snapshot_receiver.try_recv() {
+                Ok(latest) => {
+                    snapshot = Some(latest);
+                    needs_redraw = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+
+        if let (true, Some(snapshot)) = (needs_redraw, &snapshot) {
+            let world = snapshot.to_world(config.map.as_ref());
+            let level = config.speed_curve().level(world.max_food_eaten());
+            draw(&mut stdout, &world, level, config.tick_rate(level), &help, None)?;
+            needs_redraw = false;
+        }
+    }
+}
+
+fn is_quit(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => true,
+        // Raw mode swallows the interrupt signal, so Ctrl-C has to be handled by hand
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+/// The high scores for the mode of a finished game, and the rank it reached if any
+struct HighScoreList<'a> {
+    mode_key: &'a str,
+    scores: &'a [HighScore],
+    rank: Option<usize>,
+    /// Why the table could not be saved, if it could not
+    save_error: Option<&'a str>,
+}
+
+/// Draws the board in a box-drawing frame, a status line, and the high scores once the game
+/// is over
+fn draw(
+    stdout: &mut Stdout,
+    world: &World,
+    level: usize,
+    tick_rate: f64,
+    help: &str,
+    high_score_table: Option<HighScoreList>,
+) -> io::Result<()> {
+    let horizontal_border = "─".repeat(world.col_count * TILE_WIDTH);
+    queue!(stdout, cursor::MoveTo(0, 0), Print(format!("┌{}┐", horizontal_border)))?;
+
+    for i_row in 0..world.row_count {
+        queue!(stdout, cursor::MoveTo(0, i_row as u16 + 1), Print("│"))?;
+
+        for i_col in 0..world.col_count {
+            if let Some(i) = world.snakes.iter().position(|snake| snake.body.contains(&[i_row, i_col])) {
+                let color = if world.snakes[i].is_alive() { COLOR_SNAKES[i] } else { COLOR_DEAD_SNAKE };
+                queue!(stdout, SetForegroundColor(color), Print(TILE_SNAKE), ResetColor)?;
+            } else if world.food == Some([i_row, i_col]) {
+                queue!(stdout, SetForegroundColor(COLOR_FOOD), Print(TILE_FOOD), ResetColor)?;
+            } else if world.is_obstacle([i_row, i_col]) {
+                queue!(stdout, SetForegroundColor(COLOR_OBSTACLE), Print(TILE_OBSTACLE), ResetColor)?;
+            } else {
+                queue!(stdout, Print(TILE_EMPTY))?;
+            }
+        }
+
+        queue!(stdout, Print("│"))?;
+    }
+
+    let mut lines = vec![
+        format!("└{}┘", horizontal_border),
+        format!(
+            "Level {}  {:.0} ticks/s  Ticks {}  Seed {}{}",
+            level,
+            tick_rate,
+            world.tick,
+            world.seed,
+            if world.is_running || world.is_game_over() { "" } else { "  PAUSED" },
+        ),
+    ];
+
+    for (i, snake) in world.snakes.iter().enumerate() {
+        lines.push(format!(
+            "{}Food {}  Length {}{}",
+            if world.snakes.len() > 1 { format!("Player {}: ", i + 1) } else { String::new() },
+            snake.score.food_eaten,
+            snake.length(),
+            snake.death_cause.map(|cause| format!("  ({})", cause)).unwrap_or_default(),
+        ));
+    }
+
+    lines.push(String::from(help));
+
+    if world.is_game_over() {
+        lines.push(String::new());
+        lines.push(String::from("GAME OVER"));
+        if world.snakes.len() > 1 {
+            lines.push(match world.winner() {
+                Some(i) => format!("Player {} wins", i + 1),
+                None => String::from("Draw"),
+            });
+        }
+    }
+
+    if let Some(table) = high_score_table {
+        lines.push(format!("High scores ({})", table.mode_key));
+
+        for (i, high_score) in table.scores.iter().enumerate() {
+            let marker = if table.rank == Some(i) { ">" } else { " " };
+            lines.push(format!(
+                "{}{:>2}. {:>4} food  {:>4} long  {:>6} ticks",
+                marker, i + 1, high_score.food_eaten, high_score.length, high_score.ticks,
+            ));
+        }
+
+        if let Some(error) = table.save_error {
+            lines.push(String::from(error));
+        }
+    }
+
+    let first_line = world.row_count as u16 + 1;
+    for (i, line) in lines.iter().enumerate() {
+        queue!(
+            stdout,
+            cursor::MoveTo(0, first_line + i as u16),
+            terminal::Clear(terminal::ClearType::UntilNewLine),
+            Print(line),
+        )?;
+    }
+    queue!(stdout, terminal::Clear(terminal::ClearType::FromCursorDown))?;
+
+    stdout.flush()
+}
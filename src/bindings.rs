@@ -0,0 +1,461 @@
+//! Which keys and gamepad controls trigger each action of the window front-end.
+//!
+//! ```toml
+//! [bindings]
+//! preset = "hjkl"                 # arrows, wasd or hjkl
+//! pause = ["Space", "P"]          # replaces the preset's keys for this action
+//!
+//! [bindings.gamepad]
+//! pause = [6]
+//! horizontal_axis = 0
+//! ```
+//!
+//! Keys are named as the window reports them, such as `Up`, `W`, `Space`, `Return` or
+//! `Escape`, in any case. `bindings = "wasd"` picks a preset without changing anything else.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::world::MovementDirection;
+
+/// Direction keys that ship with the game, as `(name, [up, down, left, right])`
+pub const KEY_PRESETS: [(&str, [&str; 4]); 3] = [
+    ("arrows", ["Up", "Down", "Left", "Right"]),
+    ("wasd", ["W", "S", "A", "D"]),
+    ("hjkl", ["K", "J", "H", "L"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Pause,
+    Restart,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Pause,
+        Action::Restart,
+        Action::Quit,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Pause => "pause",
+            Action::Restart => "restart",
+            Action::Quit => "quit",
+        }
+    }
+
+    /// The direction a steering action turns the snake
+    pub fn direction(self) -> Option<MovementDirection> {
+        match self {
+            Action::Up => Some(MovementDirection::Up),
+            Action::Down => Some(MovementDirection::Down),
+            Action::Left => Some(MovementDirection::Left),
+            Action::Right => Some(MovementDirection::Right),
+            Action::Pause | Action::Restart | Action::Quit => None,
+        }
+    }
+}
+
+impl From<MovementDirection> for Action {
+    fn from(direction: MovementDirection) -> Action {
+        match direction {
+            MovementDirection::Up => Action::Up,
+            MovementDirection::Down => Action::Down,
+            MovementDirection::Left => Action::Left,
+            MovementDirection::Right => Action::Right,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Action, String> {
+        Action::ALL.iter()
+            .copied()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| format!("unknown action: {} (expected one of up, down, left, right, pause, restart, quit)", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "BindingsSource", into = "BindingsSource")]
+pub struct Bindings {
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    pub pause: Vec<String>,
+    pub restart: Vec<String>,
+    pub quit: Vec<String>,
+    pub gamepad: GamepadBindings,
+}
+
+/// How bindings appear in configuration files: either the name of a preset, or a preset and
+/// the keys that replace its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum BindingsSource {
+    Preset(String),
+    Keys(Box<KeysSource>),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct KeysSource {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preset: Option<String>,
+    up: Option<Vec<String>>,
+    down: Option<Vec<String>>,
+    left: Option<Vec<String>>,
+    right: Option<Vec<String>>,
+    pause: Option<Vec<String>>,
+    restart: Option<Vec<String>>,
+    quit: Option<Vec<String>>,
+    gamepad: GamepadBindings,
+}
+
+impl TryFrom<BindingsSource> for Bindings {
+    type Error = String;
+
+    fn try_from(source: BindingsSource) -> Result<Bindings, String> {
+        let source = match source {
+            BindingsSource::Preset(preset) => KeysSource { preset: Some(preset), ..KeysSource::default() },
+            BindingsSource::Keys(source) => *source,
+        };
+
+        let preset = Bindings::preset(source.preset.as_deref().unwrap_or(KEY_PRESETS[0].0))?;
+        Ok(Bindings {
+            up: source.up.unwrap_or(preset.up),
+            down: source.down.unwrap_or(preset.down),
+            left: source.left.unwrap_or(preset.left),
+            right: source.right.unwrap_or(preset.right),
+            pause: source.pause.unwrap_or(preset.pause),
+            restart: source.restart.unwrap_or(preset.restart),
+            quit: source.quit.unwrap_or(preset.quit),
+            gamepad: source.gamepad,
+        })
+    }
+}
+
+impl From<Bindings> for BindingsSource {
+    fn from(bindings: Bindings) -> BindingsSource {
+        BindingsSource::Keys(Box::new(KeysSource {
+            preset: None,
+            up: Some(bindings.up),
+            down: Some(bindings.down),
+            left: Some(bindings.left),
+            right: Some(bindings.right),
+            pause: Some(bindings.pause),
+            restart: Some(bindings.restart),
+            quit: Some(bindings.quit),
+            gamepad: bindings.gamepad,
+        }))
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings::preset(KEY_PRESETS[0].0).unwrap()
+    }
+}
+
+impl Bindings {
+    /// The direction keys of a preset, with Space, P or Esc to pause, R to restart and Q to quit
+    pub fn preset(name: &str) -> Result<Bindings, String> {
+        let (_, [up, down, left, right]) = KEY_PRESETS.iter()
+            .find(|(preset, _)| *preset == name)
+            .ok_or_else(|| format!("unknown key bindings: {} (expected one of arrows, wasd, hjkl)", name))?;
+
+        let keys = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Ok(Bindings {
+            up: keys(&[up]),
+            down: keys(&[down]),
+            left: keys(&[left]),
+            right: keys(&[right]),
+            pause: keys(&["Space", "P", "Escape"]),
+            restart: keys(&["R"]),
+            quit: keys(&["Q"]),
+            gamepad: GamepadBindings::default(),
+        })
+    }
+
+    pub fn keys(&self, action: Action) -> &[String] {
+        match action {
+            Action::Up => &self.up,
+            Action::Down => &self.down,
+            Action::Left => &self.left,
+            Action::Right => &self.right,
+            Action::Pause => &self.pause,
+            Action::Restart => &self.restart,
+            Action::Quit => &self.quit,
+        }
+    }
+
+    /// Every action bound to the key called `key`
+    pub fn actions_for_key(&self, key: &str) -> Vec<Action> {
+        Action::ALL.iter()
+            .copied()
+            .filter(|action| self.keys(*action).iter().any(|name| name.eq_ignore_ascii_case(key)))
+            .collect()
+    }
+
+    /// Direction keys for a second player at the same keyboard: the first preset that shares
+    /// no keys with these bindings, or none if every preset clashes
+    pub fn second_player(&self) -> Option<Bindings> {
+        KEY_PRESETS.iter()
+            .filter_map(|(name, _)| Bindings::preset(name).ok())
+            .find(|preset| {
+                Action::ALL.iter()
+                    .filter(|action| action.direction().is_some())
+                    .flat_map(|action| preset.keys(*action))
+                    .all(|key| self.actions_for_key(key).is_empty())
+            })
+    }
+
+    /// Checks every key name with `is_key`, which knows the names the window uses, and that no
+    /// key is bound to two actions
+    pub fn validate<F: Fn(&str) -> bool>(&self, is_key: F) -> Result<(), String> {
+        for action in Action::ALL.iter() {
+            if let Some(key) = self.keys(*action).iter().find(|key| !is_key(key)) {
+                return Err(format!("unknown key for {}: {}", action, key));
+            }
+        }
+
+        for (i, action) in Action::ALL.iter().enumerate() {
+            for key in self.keys(*action) {
+                let other = Action::ALL[i + 1..].iter()
+                    .find(|other| self.keys(**other).iter().any(|name| name.eq_ignore_ascii_case(key)));
+                if let Some(other) = other {
+                    return Err(format!("{} is bound to both {} and {}", key, action, other));
+                }
+            }
+        }
+
+        self.gamepad.validate()
+    }
+}
+
+/// Gamepad buttons by the numbers the window reports for them, and the axes that steer. The
+/// defaults follow the SDL game controller layout, where 0 is A, 6 is Start and 11 to 14 are
+/// the d-pad.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamepadBindings {
+    pub up: Vec<u8>,
+    pub down: Vec<u8>,
+    pub left: Vec<u8>,
+    pub right: Vec<u8>,
+    pub pause: Vec<u8>,
+    pub restart: Vec<u8>,
+    pub quit: Vec<u8>,
+    /// Picks the selected menu item, as Return does on the keyboard
+    pub select: Vec<u8>,
+    /// Steers left when pushed below zero and right when pushed above
+    pub horizontal_axis: Option<u8>,
+    /// Steers up when pushed below zero and down when pushed above
+    pub vertical_axis: Option<u8>,
+    /// How far from the centre an axis must be pushed to steer, from 0 to 1
+    pub dead_zone: f64,
+}
+
+impl Default for GamepadBindings {
+    fn default() -> GamepadBindings {
+        GamepadBindings {
+            up: vec![11],
+            down: vec![12],
+            left: vec![13],
+            right: vec![14],
+            pause: vec![6],
+            restart: vec![4],
+            quit: Vec::new(),
+            select: vec![0],
+            horizontal_axis: Some(0),
+            vertical_axis: Some(1),
+            dead_zone: 0.5,
+        }
+    }
+}
+
+impl GamepadBindings {
+    pub fn buttons(&self, action: Action) -> &[u8] {
+        match action {
+            Action::Up => &self.up,
+            Action::Down => &self.down,
+            Action::Left => &self.left,
+            Action::Right => &self.right,
+            Action::Pause => &self.pause,
+            Action::Restart => &self.restart,
+            Action::Quit => &self.quit,
+        }
+    }
+
+    pub fn actions_for_button(&self, button: u8) -> Vec<Action> {
+        Action::ALL.iter()
+            .copied()
+            .filter(|action| self.buttons(*action).contains(&button))
+            .collect()
+    }
+
+    /// The direction `axis` steers when pushed to `position`, if it is bound and pushed past
+    /// the dead zone
+    pub fn axis_direction(&self, axis: u8, position: f64) -> Option<MovementDirection> {
+        if position.abs() < self.dead_zone {
+            return None;
+        }
+
+        if self.horizontal_axis == Some(axis) {
+            Some(if position < 0.0 { MovementDirection::Left } else { MovementDirection::Right })
+        } else if self.vertical_axis == Some(axis) {
+            Some(if position < 0.0 { MovementDirection::Up } else { MovementDirection::Down })
+        } else {
+            None
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.dead_zone.is_finite() || self.dead_zone <= 0.0 || self.dead_zone >= 1.0 {
+            return Err(String::from("the gamepad dead zone must be above 0 and below 1"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct ConfigFile {
+        bindings: Bindings,
+    }
+
+    fn parse(text: &str) -> Result<Bindings, String> {
+        toml::from_str::<ConfigFile>(text)
+            .map(|file| file.bindings)
+            .map_err(|error| error.to_string())
+    }
+
+    fn is_key(name: &str) -> bool {
+        ["Up", "Down", "Left", "Right", "W", "A", "S", "D", "H", "J", "K", "L", "Space", "P", "Escape", "R", "Q"]
+            .iter()
+            .any(|key| key.eq_ignore_ascii_case(name))
+    }
+
+    #[test]
+    fn presets_bind_their_direction_keys() {
+        for (name, [up, down, left, right]) in KEY_PRESETS.iter() {
+            let bindings = Bindings::preset(name).unwrap();
+            assert_eq!(bindings.actions_for_key(up), vec![Action::Up]);
+            assert_eq!(bindings.actions_for_key(down), vec![Action::Down]);
+            assert_eq!(bindings.actions_for_key(left), vec![Action::Left]);
+            assert_eq!(bindings.actions_for_key(right), vec![Action::Right]);
+            assert_eq!(bindings.actions_for_key("escape"), vec![Action::Pause]);
+            assert_eq!(bindings.validate(is_key), Ok(()));
+        }
+
+        assert_eq!(Bindings::default(), Bindings::preset("arrows").unwrap());
+        assert!(Bindings::preset("ijkl").unwrap_err().contains("unknown key bindings: ijkl"));
+    }
+
+    #[test]
+    fn second_player_takes_the_first_preset_that_does_not_clash() {
+        assert_eq!(Bindings::default().second_player(), Some(Bindings::preset("wasd").unwrap()));
+
+        let mut bindings = Bindings::preset("wasd").unwrap();
+        bindings.pause = vec![String::from("Up")];
+        assert_eq!(bindings.second_player(), Some(Bindings::preset("hjkl").unwrap()));
+    }
+
+    #[test]
+    fn a_preset_name_parses_on_its_own() {
+        assert_eq!(parse("bindings = \"hjkl\"").unwrap(), Bindings::preset("hjkl").unwrap());
+        assert!(parse("bindings = \"ijkl\"").is_err());
+    }
+
+    #[test]
+    fn keys_replace_the_presets_own() {
+        let bindings = parse(
+            "[bindings]\n\
+             preset = \"wasd\"\n\
+             pause = [\"P\"]\n\
+             [bindings.gamepad]\n\
+             quit = [5]\n\
+             dead_zone = 0.25\n",
+        )
+        .unwrap();
+
+        assert_eq!(bindings.up, vec!["W"]);
+        assert_eq!(bindings.pause, vec!["P"]);
+        assert_eq!(bindings.quit, vec!["Q"]);
+        assert_eq!(bindings.gamepad.actions_for_button(5), vec![Action::Quit]);
+        assert_eq!(bindings.gamepad.up, GamepadBindings::default().up);
+        assert_eq!(bindings.gamepad.dead_zone, 0.25);
+
+        // Without a preset the keys go on top of the arrows
+        assert_eq!(parse("[bindings]\nquit = [\"Escape\"]").unwrap().up, vec!["Up"]);
+    }
+
+    #[test]
+    fn bindings_survive_a_round_trip() {
+        let mut bindings = Bindings::preset("hjkl").unwrap();
+        bindings.gamepad.horizontal_axis = None;
+        let json = serde_json::to_string(&bindings).unwrap();
+        assert_eq!(serde_json::from_str::<Bindings>(&json).unwrap(), bindings);
+    }
+
+    #[test]
+    fn unknown_fields_are_refused() {
+        assert!(parse("[bindings]\njump = [\"Space\"]").is_err());
+        assert!(parse("[bindings.gamepad]\njump = [0]").is_err());
+    }
+
+    #[test]
+    fn validate_refuses_unknown_and_duplicate_keys() {
+        let bindings = Bindings { restart: vec![String::from("F13")], ..Bindings::default() };
+        assert_eq!(bindings.validate(is_key), Err(String::from("unknown key for restart: F13")));
+
+        let bindings = Bindings { quit: vec![String::from("space")], ..Bindings::default() };
+        assert_eq!(bindings.validate(is_key), Err(String::from("Space is bound to both pause and quit")));
+
+        let mut bindings = Bindings::default();
+        bindings.gamepad.dead_zone = 1.0;
+        assert!(bindings.validate(is_key).unwrap_err().contains("dead zone"));
+    }
+
+    #[test]
+    fn axes_steer_past_the_dead_zone() {
+        let gamepad = GamepadBindings::default();
+        assert_eq!(gamepad.axis_direction(0, -0.4), None);
+        assert_eq!(gamepad.axis_direction(0, -0.6), Some(MovementDirection::Left));
+        assert_eq!(gamepad.axis_direction(0, 0.5), Some(MovementDirection::Right));
+        assert_eq!(gamepad.axis_direction(1, -0.9), Some(MovementDirection::Up));
+        assert_eq!(gamepad.axis_direction(1, 0.9), Some(MovementDirection::Down));
+        assert_eq!(gamepad.axis_direction(2, 1.0), None);
+    }
+}
@@ -0,0 +1,286 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::Bindings;
+use crate::controller::CONTROLLER_NAMES;
+use crate::level::{Difficulty, SpeedCurve, SpeedCurves};
+use crate::map::Map;
+use crate::world::BoundaryMode;
+
+pub const DEFAULT_ROW_COUNT: usize = 24;
+pub const DEFAULT_COL_COUNT: usize = 32;
+pub const DEFAULT_TILE_SIZE: f64 = 20.0;
+/// The + and - keys cannot push the game beyond these rates, and configured rates must lie between them
+pub const MIN_TICK_RATE: f64 = 0.5;
+pub const MAX_TICK_RATE: f64 = 240.0;
+
+/// The starting snake is three tiles long and lies along the top row, so anything narrower
+/// would make the game unwinnable from the first tick
+pub const MIN_ROW_COUNT: usize = 2;
+pub const MIN_COL_COUNT: usize = 4;
+
+/// Each player starts on a row of their own, and the front-ends have colours for this many
+pub const MAX_PLAYERS: usize = 4;
+
+pub const USAGE: &str = "\
+usage: rust-snake [options]
+
+options:
+    --config <file>        TOML file with any of the settings below and the speed curves;
+                           options given on the command line take precedence
+    --seed <number>        seed for food placement, to reproduce an earlier game
+    --boundary <mode>      wrap, walls, wrap-horizontal or wrap-vertical
+    --preset <name>        board size preset: small, medium or large
+    --rows <number>        number of board rows
+    --cols <number>        number of board columns
+    --tile-size <pixels>   initial size of one tile in the window
+    --fps                  show the frame rate in the window's HUD
+    --bindings <preset>    steering keys: arrows, wasd or hjkl; a config file can also bind
+                           pause, restart, quit and gamepad controls
+    --map <name|file>      play on a map with walls: box, cross, rooms, pillars or a map
+                           file; the map sets the board size
+    --difficulty <name>    easy, normal or hard
+    --tick-rate <number>   fixed simulation ticks per second, ignoring the speed curve
+    --players <number>     how many snakes share the board, 1 to 4; player 1 steers with
+                           the bound keys, player 2 with the first preset that does not
+                           clash with them, and the rest are bots
+    --ai <name>            let a built-in bot play: random, greedy, bfs or hamiltonian;
+                           with several players, the bot steers every snake
+    --record <file>        save a replay of each finished game to this file
+    --new-game             ignore the game saved when the window was last closed
+    --replay <file>        play back a recorded game: space pauses, . steps one tick,
+                           + and - change the speed, R starts over";
+
+/// Named board sizes, as `(name, rows, cols)`
+pub const PRESETS: [(&str, usize, usize); 3] = [
+    ("small", 15, 20),
+    ("medium", DEFAULT_ROW_COUNT, DEFAULT_COL_COUNT),
+    ("large", 36, 48),
+];
+
+/// Runtime settings shared by the rules and the front-ends
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(rename = "rows")]
+    pub row_count: usize,
+    #[serde(rename = "cols")]
+    pub col_count: usize,
+    pub tile_size: f64,
+    /// Show a frame-rate counter in the window's HUD
+    pub show_fps: bool,
+    /// Keys and gamepad controls of the window front-end
+    pub bindings: Bindings,
+    /// Overrides the speed curve with a constant rate when set
+    pub tick_rate: Option<f64>,
+    pub boundary: BoundaryMode,
+    /// Walls and start positions; the map's size replaces the configured board size
+    pub map: Option<Map>,
+    pub seed: Option<u64>,
+    pub difficulty: Difficulty,
+    pub speed_curves: SpeedCurves,
+    /// How many snakes share the board
+    pub players: usize,
+    /// A built-in controller that plays instead of the keyboard
+    pub ai: Option<String>,
+    /// Where to save a replay of each finished game
+    #[serde(skip)]
+    pub record_path: Option<PathBuf>,
+    /// A replay to play back instead of starting a new game
+    #[serde(skip)]
+    pub replay_path: Option<PathBuf>,
+    /// Start a new game even if one was saved when the window was last closed
+    #[serde(skip)]
+    pub new_game: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            row_count: DEFAULT_ROW_COUNT,
+            col_count: DEFAULT_COL_COUNT,
+            tile_size: DEFAULT_TILE_SIZE,
+            show_fps: false,
+            bindings: Bindings::default(),
+            tick_rate: None,
+            boundary: BoundaryMode::default(),
+            map: None,
+            seed: None,
+            difficulty: Difficulty::default(),
+            speed_curves: SpeedCurves::default(),
+            players: 1,
+            ai: None,
+            record_path: None,
+            replay_path: None,
+            new_game: false,
+        }
+    }
+}
+
+impl Config {
+    /// Builds a configuration from command-line arguments, excluding the program name.
+    /// A `--config` file is applied first, wherever it appears, so that other arguments override it.
+    pub fn from_args<I: Iterator<Item=String>>(args: I) -> Result<Config, String> {
+        let args: Vec<String> = args.collect();
+        let mut config = match args.iter().position(|arg| arg == "--config") {
+            Some(i) => Config::load(Path::new(&parse_value::<String>("--config", args.get(i + 1).cloned())?))?,
+            None => Config::default(),
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => { args.next(); }
+                "--seed" => config.seed = Some(parse_value(&arg, args.next())?),
+                "--boundary" => config.boundary = parse_value(&arg, args.next())?,
+                "--preset" => config.apply_preset(&parse_value::<String>(&arg, args.next())?)?,
+                "--rows" => config.row_count = parse_value(&arg, args.next())?,
+                "--cols" => config.col_count = parse_value(&arg, args.next())?,
+                "--map" => config.map = Some(Map::find(&parse_value::<String>(&arg, args.next())?)?),
+                "--tile-size" => config.tile_size = parse_value(&arg, args.next())?,
+                "--fps" => config.show_fps = true,
+                "--bindings" => config.bindings = Bindings::preset(&parse_value::<String>(&arg, args.next())?)?,
+                "--tick-rate" => config.tick_rate = Some(parse_value(&arg, args.next())?),
+                "--difficulty" => config.difficulty = parse_value(&arg, args.next())?,
+                "--players" => config.players = parse_value(&arg, args.next())?,
+                "--ai" => config.ai = Some(parse_value(&arg, args.next())?),
+                "--record" => config.record_path = Some(parse_value(&arg, args.next())?),
+                "--replay" => config.replay_path = Some(parse_value(&arg, args.next())?),
+                "--new-game" => config.new_game = true,
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+
+        config.apply_map();
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a configuration file. Settings it leaves out keep their defaults.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("could not read {}: {}", path.display(), error))?;
+        let mut config: Config = toml::from_str(&contents)
+            .map_err(|error| format!("invalid config file {}: {}", path.display(), error))?;
+        config.apply_map();
+        Ok(config)
+    }
+
+    /// Resizes the board to fit the map, if there is one
+    pub fn apply_map(&mut self) {
+        if let Some(map) = &self.map {
+            self.row_count = map.row_count;
+            self.col_count = map.col_count;
+        }
+    }
+
+    pub fn apply_preset(&mut self, name: &str) -> Result<(), String> {
+        let (_, rows, cols) = PRESETS.iter()
+            .find(|(preset, _, _)| *preset == name)
+            .ok_or_else(|| format!("unknown preset: {} (expected one of small, medium, large)", name))?;
+
+        self.row_count = *rows;
+        self.col_count = *cols;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.row_count < MIN_ROW_COUNT || self.col_count < MIN_COL_COUNT {
+            return Err(format!("the board must be at least {} rows by {} columns", MIN_ROW_COUNT, MIN_COL_COUNT));
+        }
+
+        if !self.tile_size.is_finite() || self.tile_size <= 0.0 {
+            return Err(String::from("the tile size must be positive"));
+        }
+
+        if let Some(tick_rate) = self.tick_rate {
+            if !tick_rate.is_finite() || !(MIN_TICK_RATE..=MAX_TICK_RATE).contains(&tick_rate) {
+                return Err(format!("the tick rate must be between {} and {}", MIN_TICK_RATE, MAX_TICK_RATE));
+            }
+        }
+
+        if self.players == 0 || self.players > MAX_PLAYERS {
+            return Err(format!("the number of players must be between 1 and {}", MAX_PLAYERS));
+        }
+
+        match &self.map {
+            Some(map) if self.players > map.starts.len() => {
+                return Err(format!("the {} map has room for {} players at most", map.name, map.starts.len()));
+            }
+            Some(map) if map.row_count != self.row_count || map.col_count != self.col_count => {
+                return Err(format!("the {} map needs a board of {} rows by {} columns", map.name, map.row_count, map.col_count));
+            }
+            Some(_) => {}
+            None if self.players > self.row_count => {
+                return Err(String::from("the board needs at least one row per player"));
+            }
+            None => {}
+        }
+
+        if let Some(ai) = &self.ai {
+            if !CONTROLLER_NAMES.contains(&ai.as_str()) {
+                return Err(format!("unknown AI: {} (expected one of {})", ai, CONTROLLER_NAMES.join(", ")));
+            }
+        }
+
+        self.bindings.gamepad.validate()?;
+
+        for difficulty in Difficulty::ALL.iter() {
+            self.speed_curves.get(*difficulty)
+                .validate(MIN_TICK_RATE, MAX_TICK_RATE)
+                .map_err(|error| format!("invalid {} speed curve: {}", difficulty, error))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn speed_curve(&self) -> &SpeedCurve {
+        self.speed_curves.get(self.difficulty)
+    }
+
+    /// The tick rate at `level`, following the speed curve unless a fixed rate was configured
+    pub fn tick_rate(&self, level: usize) -> f64 {
+        self.tick_rate.unwrap_or_else(|| self.speed_curve().tick_rate(level))
+    }
+
+    /// The tick rate at `level` sped up or slowed down by `speed_multiplier`, kept between
+    /// `MIN_TICK_RATE` and `MAX_TICK_RATE` however far the multiplier goes
+    pub fn scaled_tick_rate(&self, level: usize, speed_multiplier: f64) -> f64 {
+        (self.tick_rate(level) * speed_multiplier).clamp(MIN_TICK_RATE, MAX_TICK_RATE)
+    }
+
+    /// The window size, in pixels, that fits the whole board at the configured tile size
+    pub fn window_size(&self) -> [f64; 2] {
+        [self.col_count as f64 * self.tile_size, self.row_count as f64 * self.tile_size]
+    }
+}
+
+/// Parses the value given after `flag` on the command line, which every binary's options share
+pub fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", flag))?;
+    value.parse().map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_tick_rate_stays_in_range() {
+        let config = Config { tick_rate: Some(10.0), ..Config::default() };
+        assert_eq!(config.scaled_tick_rate(1, 2.0), 20.0);
+        assert_eq!(config.scaled_tick_rate(1, 1e-12), MIN_TICK_RATE);
+        assert_eq!(config.scaled_tick_rate(1, 1e12), MAX_TICK_RATE);
+    }
+
+    #[test]
+    fn tick_rate_must_be_in_range() {
+        let args = |rate: &str| vec![String::from("--tick-rate"), rate.to_string()].into_iter();
+        assert!(Config::from_args(args("10")).is_ok());
+        assert!(Config::from_args(args("0.001")).is_err());
+        assert!(Config::from_args(args("1000")).is_err());
+    }
+}
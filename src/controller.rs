@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::ai::{GreedyController, HamiltonianController, PathfindingController};
+use crate::world::{MovementDirection, WorldView, INPUT_QUEUE_CAPACITY};
+
+/// Something that steers a snake: a player at the keyboard, a bot, a replay or a remote peer.
+/// It is consulted once before every tick the snake is alive for.
+pub trait Controller {
+    /// Returns the heading for the next tick. Returning `view.heading` keeps the snake going
+    /// straight, and reversing onto the snake's own neck is ignored.
+    fn next_direction(&mut self, view: &WorldView) -> MovementDirection;
+}
+
+/// Names accepted by `controller_by_name`
+pub const CONTROLLER_NAMES: [&str; 4] = ["random", "greedy", "bfs", "hamiltonian"];
+
+/// The bot the front-ends use for players who have no keys of their own
+pub const DEFAULT_BOT: &str = "bfs";
+
+/// Creates one of the built-in controllers. `seed` makes randomized controllers reproducible.
+pub fn controller_by_name(name: &str, seed: u64) -> Option<Box<dyn Controller>> {
+    bot_by_name(name, seed).map(|bot| bot as Box<dyn Controller>)
+}
+
+/// Like `controller_by_name`, for callers that move the controller to another thread
+pub fn bot_by_name(name: &str, seed: u64) -> Option<Box<dyn Controller + Send>> {
+    match name {
+        "random" => Some(Box::new(RandomController::new(seed))),
+        "greedy" => Some(Box::new(GreedyController)),
+        "bfs" => Some(Box::new(PathfindingController)),
+        "hamiltonian" => Some(Box::new(HamiltonianController::new())),
+        _ => None,
+    }
+}
+
+/// Buffers direction key presses from a front-end and hands them out one per tick, so that two
+/// quick presses within one tick make two turns instead of the second overwriting the first
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardController {
+    pending: VecDeque<MovementDirection>,
+}
+
+impl KeyboardController {
+    pub fn new() -> KeyboardController {
+        KeyboardController::default()
+    }
+
+    pub fn press(&mut self, direction: MovementDirection) {
+        if self.pending.back() != Some(&direction) && self.pending.len() < INPUT_QUEUE_CAPACITY {
+            self.pending.push_back(direction);
+        }
+    }
+
+    /// Drops presses that have not been applied yet, e.g. when the game restarts
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+impl Controller for KeyboardController {
+    fn next_direction(&mut self, view: &WorldView) -> MovementDirection {
+        // Presses that would reverse or repeat the heading are skipped rather than wasting a tick
+        while let Some(direction) = self.pending.pop_front() {
+            if direction != view.heading && direction != view.heading.opposite() {
+                return direction;
+            }
+        }
+
+        view.heading
+    }
+}
+
+/// Turns at random, avoiding moves that would kill the snake on the next tick when it can.
+/// Useful as a baseline that any real bot should beat.
+#[derive(Debug, Clone)]
+pub struct RandomController {
+    rng: ChaCha8Rng,
+}
+
+/// How often the random controller turns when it does not have to
+const RANDOM_TURN_PROBABILITY: f64 = 0.1;
+
+impl RandomController {
+    pub fn new(seed: u64) -> RandomController {
+        RandomController { rng: ChaCha8Rng::seed_from_u64(seed) }
+    }
+}
+
+impl Controller for RandomController {
+    fn next_direction(&mut self, view: &WorldView) -> MovementDirection {
+        let heading = view.heading;
+        let head = match view.head() {
+            Some(head) => head,
+            None => return heading,
+        };
+        let is_safe = |direction: MovementDirection| {
+            view.next_tile(head, direction)
+                .map(|tile| !view.is_blocked(tile))
+                .unwrap_or(false)
+        };
+
+        if is_safe(heading) && !self.rng.gen_bool(RANDOM_TURN_PROBABILITY) {
+            return heading;
+        }
+
+        let turns = [heading.turn_left(), heading.turn_right()];
+        let safe_turns: Vec<MovementDirection> = turns.iter().copied().filter(|turn| is_safe(*turn)).collect();
+        safe_turns.choose(&mut self.rng).copied().unwrap_or(heading)
+    }
+}
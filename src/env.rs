@@ -0,0 +1,581 @@
+//! A reinforcement-learning environment over `World`, in the style of OpenAI Gym: `reset` starts
+//! an episode and `step` plays one tick with the agent's action, returning an observation, a
+//! reward, whether the episode is done, and some information about the game. The agent steers
+//! snake 0; with several players, built-in bots steer the others.
+
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai;
+use crate::config::Config;
+use crate::controller::{self, Controller, DEFAULT_BOT};
+use crate::world::{DeathCause, MovementDirection, World, WorldView};
+
+/// The actions an agent can take, in the order their indices refer to
+pub const ACTIONS: [MovementDirection; 4] = [
+    MovementDirection::Up,
+    MovementDirection::Left,
+    MovementDirection::Down,
+    MovementDirection::Right,
+];
+
+/// Channels of a grid observation, in order
+pub const GRID_CHANNELS: [&str; 5] = ["head", "body", "opponents", "food", "obstacles"];
+
+/// The directions rays are cast in, clockwise from straight up, as vertical and horizontal steps
+const RAY_DIRECTIONS: [(Option<MovementDirection>, Option<MovementDirection>); 8] = [
+    (Some(MovementDirection::Up), None),
+    (Some(MovementDirection::Up), Some(MovementDirection::Right)),
+    (None, Some(MovementDirection::Right)),
+    (Some(MovementDirection::Down), Some(MovementDirection::Right)),
+    (Some(MovementDirection::Down), None),
+    (Some(MovementDirection::Down), Some(MovementDirection::Left)),
+    (None, Some(MovementDirection::Left)),
+    (Some(MovementDirection::Up), Some(MovementDirection::Left)),
+];
+
+/// What the agent gets to see after every step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ObservationKind {
+    /// One `rows` by `cols` plane per entry of `GRID_CHANNELS`, with 1 where the channel applies
+    #[default]
+    Grid,
+    /// For each of eight directions, the inverse distance to the nearest obstacle, snake and
+    /// food along a ray from the head, followed by the heading as four flags
+    Rays,
+    /// Danger straight ahead, to the left and to the right; the heading as four flags; and
+    /// whether the food is up, left, down or right of the head
+    Compact,
+}
+
+impl ObservationKind {
+    pub const ALL: [ObservationKind; 3] = [ObservationKind::Grid, ObservationKind::Rays, ObservationKind::Compact];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ObservationKind::Grid => "grid",
+            ObservationKind::Rays => "rays",
+            ObservationKind::Compact => "compact",
+        }
+    }
+
+    /// The shape of the observation on a board of the given size
+    pub fn shape(self, row_count: usize, col_count: usize) -> Vec<usize> {
+        match self {
+            ObservationKind::Grid => vec![GRID_CHANNELS.len(), row_count, col_count],
+            ObservationKind::Rays => vec![RAY_DIRECTIONS.len() * 3 + ACTIONS.len()],
+            ObservationKind::Compact => vec![3 + ACTIONS.len() * 2],
+        }
+    }
+}
+
+impl fmt::Display for ObservationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ObservationKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ObservationKind, String> {
+        ObservationKind::ALL.iter()
+            .copied()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| format!("unknown observation: {} (expected one of grid, rays, compact)", s))
+    }
+}
+
+/// A tensor of features, stored flat in row-major order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+/// How much each event is worth to the agent. Every reward is added up on the tick it happens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rewards {
+    pub food: f32,
+    pub death: f32,
+    /// Given every tick the snake survives, usually a small penalty to discourage dawdling
+    pub step: f32,
+    /// Given for outliving every other snake
+    pub win: f32,
+    /// Given for a step that brings the head closer to the food, and taken for one that moves
+    /// it away
+    pub approach: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards {
+            food: 1.0,
+            death: -1.0,
+            step: 0.0,
+            win: 1.0,
+            approach: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvOptions {
+    /// The game settings; the seed only applies to the first episode unless `reset` is given one
+    pub config: Config,
+    pub observation: ObservationKind,
+    pub rewards: Rewards,
+    /// Episodes are cut short after this many ticks, or never
+    pub max_ticks: Option<u64>,
+}
+
+impl EnvOptions {
+    pub fn new(config: Config) -> EnvOptions {
+        EnvOptions {
+            config,
+            observation: ObservationKind::default(),
+            rewards: Rewards::default(),
+            max_ticks: None,
+        }
+    }
+}
+
+/// What happened during one step, besides the reward
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepInfo {
+    pub tick: u64,
+    pub food_eaten: u32,
+    pub length: usize,
+    pub death_cause: Option<DeathCause>,
+    /// Whether the episode ended because of the tick limit rather than the game ending
+    pub truncated: bool,
+    /// The seed of the episode this step belongs to
+    pub seed: u64,
+    /// The last observation of an episode that `VecEnv` has already reset
+    pub final_observation: Option<Observation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepResult {
+    pub observation: Observation,
+    pub reward: f32,
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+/// Steers the agent's snake with the action passed to `Env::step`
+struct Action(MovementDirection);
+
+impl Controller for Action {
+    fn next_direction(&mut self, _view: &WorldView) -> MovementDirection {
+        self.0
+    }
+}
+
+pub struct Env {
+    pub options: EnvOptions,
+    world: World,
+    /// The bots steering snakes 1 and up
+    opponents: Vec<Box<dyn Controller + Send>>,
+}
+
+impl Env {
+    pub fn new(options: EnvOptions) -> Env {
+        let world = World::from_config(&options.config);
+        let mut env = Env { options, world, opponents: Vec::new() };
+        env.reset_opponents();
+        env
+    }
+
+    /// Starts a new episode and returns its first observation. Without a seed, the new episode's
+    /// seed is drawn from the previous one, so a sequence of episodes is reproducible.
+    pub fn reset(&mut self, seed: Option<u64>) -> Observation {
+        match seed {
+            Some(seed) => self.world = World::from_config(&Config { seed: Some(seed), ..self.options.config.clone() }),
+            None => self.world.restart(),
+        }
+        self.reset_opponents();
+        self.observe()
+    }
+
+    fn reset_opponents(&mut self) {
+        let name = self.options.config.ai.as_deref().unwrap_or(DEFAULT_BOT);
+        let seed = self.world.seed;
+        self.opponents = (1..self.world.snakes.len())
+            .map(|i_player| controller::bot_by_name(name, seed.wrapping_add(i_player as u64)).unwrap())
+            .collect();
+    }
+
+    /// Plays one tick with the agent heading in `action`. Reversing onto the neck is ignored, like
+    /// any other attempt to reverse. Stepping after the episode is done changes nothing.
+    pub fn step(&mut self, action: MovementDirection) -> StepResult {
+        let rewards = &self.options.rewards;
+        let food_eaten = self.world.snakes[0].score.food_eaten;
+        let food_distance = self.food_distance();
+        let was_done = self.is_done();
+
+        let mut agent = Action(action);
+        let mut controllers: Vec<&mut dyn Controller> = vec![&mut agent];
+        controllers.extend(self.opponents.iter_mut().map(|opponent| opponent.as_mut() as &mut dyn Controller));
+        self.world.step_with(&mut controllers);
+
+        let snake = &self.world.snakes[0];
+        let mut reward = 0.0;
+        if !was_done {
+            reward += (snake.score.food_eaten - food_eaten) as f32 * rewards.food;
+            if !snake.is_alive() {
+                reward += rewards.death;
+            } else {
+                reward += rewards.step;
+                if self.world.winner() == Some(0) {
+                    reward += rewards.win;
+                }
+
+                if snake.score.food_eaten == food_eaten {
+                    if let (Some(before), Some(after)) = (food_distance, self.food_distance()) {
+                        reward += rewards.approach * (before as f32 - after as f32).signum();
+                    }
+                }
+            }
+        }
+
+        StepResult {
+            observation: self.observe(),
+            reward,
+            done: self.is_done(),
+            info: StepInfo {
+                tick: self.world.tick,
+                food_eaten: snake.score.food_eaten,
+                length: snake.length(),
+                death_cause: snake.death_cause,
+                truncated: !self.world.is_game_over() && self.is_truncated(),
+                seed: self.world.seed,
+                final_observation: None,
+            },
+        }
+    }
+
+    /// Whether the agent's snake has died, the game is over, or the tick limit was reached
+    pub fn is_done(&self) -> bool {
+        !self.world.snakes[0].is_alive() || self.world.is_game_over() || self.is_truncated()
+    }
+
+    fn is_truncated(&self) -> bool {
+        self.options.max_ticks.is_some_and(|max_ticks| self.world.tick >= max_ticks)
+    }
+
+    /// The game being played, for inspection and rendering
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn observation_shape(&self) -> Vec<usize> {
+        self.options.observation.shape(self.world.row_count, self.world.col_count)
+    }
+
+    /// What the agent sees of the current state
+    pub fn observe(&self) -> Observation {
+        let view = self.world.view(0);
+        let data = match self.options.observation {
+            ObservationKind::Grid => grid_features(&view),
+            ObservationKind::Rays => ray_features(&view),
+            ObservationKind::Compact => compact_features(&view),
+        };
+
+        Observation { shape: self.observation_shape(), data }
+    }
+
+    fn food_distance(&self) -> Option<usize> {
+        let view = self.world.view(0);
+        Some(ai::distance(&view, view.head()?, view.food?))
+    }
+}
+
+fn heading_features(heading: MovementDirection) -> impl Iterator<Item=f32> {
+    ACTIONS.iter().map(move |direction| if *direction == heading { 1.0 } else { 0.0 })
+}
+
+fn grid_features(view: &WorldView) -> Vec<f32> {
+    let plane = view.row_count * view.col_count;
+    let mut data = vec![0.0; GRID_CHANNELS.len() * plane];
+    let mut set = |channel: usize, tile: [usize; 2]| data[channel * plane + tile[0] * view.col_count + tile[1]] = 1.0;
+
+    for (i, snake) in view.snakes.iter().enumerate() {
+        for tile in snake.body.iter() {
+            set(if i == view.you { 1 } else { 2 }, *tile);
+        }
+    }
+    if let Some(head) = view.head() {
+        set(0, head);
+    }
+    if let Some(food) = view.food {
+        set(3, food);
+    }
+    if let Some(map) = view.map {
+        for wall in map.walls() {
+            set(4, wall);
+        }
+    }
+
+    data
+}
+
+fn ray_features(view: &WorldView) -> Vec<f32> {
+    let mut data = Vec::with_capacity(RAY_DIRECTIONS.len() * 3 + ACTIONS.len());
+    let head = match view.head() {
+        Some(head) => head,
+        None => return vec![0.0; RAY_DIRECTIONS.len() * 3 + ACTIONS.len()],
+    };
+
+    // A ray on a wrapping board would go round forever, so it stops after crossing the board once
+    let max_steps = view.row_count.max(view.col_count);
+    for (vertical, horizontal) in RAY_DIRECTIONS.iter() {
+        let step = |tile: [usize; 2]| {
+            let tile = match vertical {
+                Some(direction) => view.next_tile(tile, *direction)?,
+                None => tile,
+            };
+            match horizontal {
+                Some(direction) => view.next_tile(tile, *direction),
+                None => Some(tile),
+            }
+        };
+
+        let (mut obstacle, mut snake, mut food) = (0.0, 0.0, 0.0);
+        let mut tile = head;
+        for distance in 1..=max_steps {
+            tile = match step(tile) {
+                Some(next) if !view.is_obstacle(next) => next,
+                _ => {
+                    obstacle = 1.0 / distance as f32;
+                    break;
+                }
+            };
+
+            if snake == 0.0 && view.snakes.iter().any(|other| other.body.contains(&tile)) {
+                snake = 1.0 / distance as f32;
+            }
+            if food == 0.0 && view.food == Some(tile) {
+                food = 1.0 / distance as f32;
+            }
+        }
+
+        data.extend_from_slice(&[obstacle, snake, food]);
+    }
+
+    data.extend(heading_features(view.heading));
+    data
+}
+
+fn compact_features(view: &WorldView) -> Vec<f32> {
+    let head = match view.head() {
+        Some(head) => head,
+        None => return vec![0.0; 3 + ACTIONS.len() * 2],
+    };
+
+    let is_danger = |direction: MovementDirection| match view.next_tile(head, direction) {
+        Some(tile) => view.is_blocked(tile),
+        None => true,
+    };
+    let flag = |is_set: bool| if is_set { 1.0 } else { 0.0 };
+
+    let mut data = vec![
+        flag(is_danger(view.heading)),
+        flag(is_danger(view.heading.turn_left())),
+        flag(is_danger(view.heading.turn_right())),
+    ];
+    data.extend(heading_features(view.heading));
+
+    // On a wrapping board, the food lies whichever way round is shorter
+    let offset = |from: usize, to: usize, size: usize, wraps: bool| {
+        let offset = to as isize - from as isize;
+        let size = size as isize;
+        if wraps && offset.abs() * 2 > size { offset - offset.signum() * size } else { offset }
+    };
+    let (rows, cols) = match view.food {
+        Some(food) => (
+            offset(head[0], food[0], view.row_count, view.boundary.wraps_rows()),
+            offset(head[1], food[1], view.col_count, view.boundary.wraps_cols()),
+        ),
+        None => (0, 0),
+    };
+    data.extend_from_slice(&[flag(rows < 0), flag(cols < 0), flag(rows > 0), flag(cols > 0)]);
+
+    data
+}
+
+/// Many environments stepped together, spread over threads. An environment whose episode ends
+/// is reset straight away: its result carries the new episode's first observation, with the
+/// last one in `info.final_observation`.
+pub struct VecEnv {
+    envs: Vec<Env>,
+    threads: usize,
+}
+
+impl VecEnv {
+    /// Creates `count` environments. They share the options, but each plays its own seeds.
+    pub fn new(options: EnvOptions, count: usize) -> VecEnv {
+        let first_seed = options.config.seed;
+        let mut envs: Vec<Env> = (0..count.max(1)).map(|_| Env::new(options.clone())).collect();
+        if let Some(seed) = first_seed {
+            for (i, env) in envs.iter_mut().enumerate() {
+                env.reset(Some(seed.wrapping_add(i as u64)));
+            }
+        }
+
+        let threads = thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1);
+        VecEnv { envs, threads }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    pub fn envs(&self) -> &[Env] {
+        &self.envs
+    }
+
+    /// Starts a new episode in every environment, with consecutive seeds from `first_seed` if
+    /// given
+    pub fn reset(&mut self, first_seed: Option<u64>) -> Vec<Observation> {
+        self.envs.iter_mut()
+            .enumerate()
+            .map(|(i, env)| env.reset(first_seed.map(|seed| seed.wrapping_add(i as u64))))
+            .collect()
+    }
+
+    /// Steps every environment with its action from `actions`, in parallel
+    pub fn step(&mut self, actions: &[MovementDirection]) -> Vec<StepResult> {
+        assert_eq!(actions.len(), self.envs.len(), "expected one action per environment");
+
+        let chunk_size = self.envs.len().div_ceil(self.threads);
+        thread::scope(|scope| {
+            let handles: Vec<_> = self.envs.chunks_mut(chunk_size)
+                .zip(actions.chunks(chunk_size))
+                .map(|(envs, actions)| scope.spawn(move || {
+                    envs.iter_mut()
+                        .zip(actions.iter())
+                        .map(|(env, action)| {
+                            let mut result = env.step(*action);
+                            if result.done {
+                                let first_observation = env.reset(None);
+                                result.info.final_observation = Some(mem::replace(&mut result.observation, first_observation));
+                            }
+                            result
+                        })
+                        .collect::<Vec<_>>()
+                }))
+                .collect();
+
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::BoundaryMode;
+
+    fn options(observation: ObservationKind) -> EnvOptions {
+        let config = Config { row_count: 8, col_count: 12, boundary: BoundaryMode::Walls, seed: Some(1), ..Config::default() };
+        EnvOptions { observation, ..EnvOptions::new(config) }
+    }
+
+    #[test]
+    fn observations_have_the_advertised_shape() {
+        let expected = [vec![5, 8, 12], vec![28], vec![11]];
+        for (kind, shape) in ObservationKind::ALL.iter().zip(expected.iter()) {
+            let mut env = Env::new(options(*kind));
+            assert_eq!(&env.observation_shape(), shape);
+
+            for observation in [env.reset(Some(2)), env.step(MovementDirection::Right).observation].iter() {
+                assert_eq!(&observation.shape, shape);
+                assert_eq!(observation.data.len(), shape.iter().product::<usize>());
+            }
+        }
+    }
+
+    #[test]
+    fn eating_is_rewarded() {
+        let mut env = Env::new(options(ObservationKind::Compact));
+        env.world.food = Some([0, 3]);
+
+        let result = env.step(MovementDirection::Right);
+        assert_eq!(result.reward, 1.0);
+        assert!(!result.done);
+        assert_eq!((result.info.food_eaten, result.info.length), (1, 4));
+    }
+
+    #[test]
+    fn dying_is_penalised_once() {
+        let mut env = Env::new(options(ObservationKind::Compact));
+        env.world.food = None;
+
+        let result = env.step(MovementDirection::Up);
+        assert_eq!(result.reward, -1.0);
+        assert!(result.done && !result.info.truncated);
+        assert_eq!(result.info.death_cause, Some(DeathCause::Wall));
+
+        let result = env.step(MovementDirection::Down);
+        assert_eq!(result.reward, 0.0);
+        assert!(result.done);
+    }
+
+    #[test]
+    fn episodes_are_truncated_at_the_tick_limit() {
+        let mut env = Env::new(EnvOptions { max_ticks: Some(2), ..options(ObservationKind::Compact) });
+        env.world.food = None;
+
+        assert!(!env.step(MovementDirection::Right).done);
+        let result = env.step(MovementDirection::Right);
+        assert!(result.done && result.info.truncated);
+        assert_eq!(result.reward, 0.0);
+    }
+
+    #[test]
+    fn vec_env_matches_independent_envs() {
+        let options = options(ObservationKind::Rays);
+        let mut vec_env = VecEnv::new(options.clone(), 3);
+        // Split the environments over threads even on a machine with one core
+        vec_env.threads = 2;
+        let mut envs: Vec<Env> = (0..3)
+            .map(|i| {
+                let mut env = Env::new(options.clone());
+                env.reset(Some(1 + i));
+                env
+            })
+            .collect();
+
+        let mut episodes_ended = 0;
+        for i_step in 0..200 {
+            let actions: Vec<MovementDirection> = (0..3).map(|i| ACTIONS[(i_step / 3 + i) % ACTIONS.len()]).collect();
+            let expected: Vec<StepResult> = envs.iter_mut()
+                .zip(actions.iter())
+                .map(|(env, action)| {
+                    let mut result = env.step(*action);
+                    if result.done {
+                        let first_observation = env.reset(None);
+                        result.info.final_observation = Some(mem::replace(&mut result.observation, first_observation));
+                    }
+                    result
+                })
+                .collect();
+
+            let results = vec_env.step(&actions);
+            episodes_ended += results.iter().filter(|result| result.done).count();
+            assert_eq!(results, expected);
+        }
+        assert!(episodes_ended > 0);
+    }
+}
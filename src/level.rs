@@ -0,0 +1,166 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Chooses which speed curve a game follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Difficulty, String> {
+        Difficulty::ALL.iter()
+            .copied()
+            .find(|difficulty| difficulty.name() == s)
+            .ok_or_else(|| format!("unknown difficulty: {} (expected one of easy, normal, hard)", s))
+    }
+}
+
+/// How the tick rate rises as the snake eats
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpeedCurve {
+    /// Food the snake must eat to advance one level
+    pub food_per_level: u32,
+    /// Ticks per second at level 1, level 2 and so on, never going down. The last rate holds
+    /// for every later level.
+    pub tick_rates: Vec<f64>,
+}
+
+impl SpeedCurve {
+    /// The level reached after eating `food_eaten` food, starting at 1
+    pub fn level(&self, food_eaten: u32) -> usize {
+        (food_eaten / self.food_per_level) as usize + 1
+    }
+
+    pub fn tick_rate(&self, level: usize) -> f64 {
+        let index = level.saturating_sub(1).min(self.tick_rates.len() - 1);
+        self.tick_rates[index]
+    }
+
+    pub fn validate(&self, min_tick_rate: f64, max_tick_rate: f64) -> Result<(), String> {
+        if self.food_per_level == 0 {
+            return Err(String::from("food_per_level must be at least 1"));
+        }
+
+        if self.tick_rates.is_empty() {
+            return Err(String::from("tick_rates must list at least one rate"));
+        }
+
+        if self.tick_rates.iter().any(|rate| !rate.is_finite() || *rate < min_tick_rate || *rate > max_tick_rate) {
+            return Err(format!("every tick rate must be between {} and {}", min_tick_rate, max_tick_rate));
+        }
+
+        if self.tick_rates.windows(2).any(|pair| pair[1] < pair[0]) {
+            return Err(String::from("tick_rates must not go down from one level to the next"));
+        }
+
+        Ok(())
+    }
+}
+
+/// One speed curve per difficulty
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeedCurves {
+    pub easy: SpeedCurve,
+    pub normal: SpeedCurve,
+    pub hard: SpeedCurve,
+}
+
+impl Default for SpeedCurves {
+    fn default() -> SpeedCurves {
+        SpeedCurves {
+            easy: SpeedCurve {
+                food_per_level: 5,
+                tick_rates: vec![6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 14.0, 16.0],
+            },
+            normal: SpeedCurve {
+                food_per_level: 5,
+                tick_rates: vec![10.0, 11.0, 12.0, 14.0, 16.0, 18.0, 20.0, 23.0, 26.0, 30.0],
+            },
+            hard: SpeedCurve {
+                food_per_level: 4,
+                tick_rates: vec![15.0, 17.0, 19.0, 21.0, 24.0, 27.0, 30.0, 34.0, 38.0, 42.0],
+            },
+        }
+    }
+}
+
+impl SpeedCurves {
+    pub fn get(&self, difficulty: Difficulty) -> &SpeedCurve {
+        match difficulty {
+            Difficulty::Easy => &self.easy,
+            Difficulty::Normal => &self.normal,
+            Difficulty::Hard => &self.hard,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(tick_rates: &[f64]) -> SpeedCurve {
+        SpeedCurve { food_per_level: 5, tick_rates: tick_rates.to_vec() }
+    }
+
+    #[test]
+    fn levels_start_at_one_and_rise_every_food_per_level() {
+        let curve = curve(&[10.0]);
+        let levels: Vec<usize> = [0, 4, 5, 9, 10, 23].iter().map(|food| curve.level(*food)).collect();
+        assert_eq!(levels, vec![1, 1, 2, 2, 3, 5]);
+    }
+
+    #[test]
+    fn tick_rate_follows_the_level_and_holds_the_last_rate() {
+        let curve = curve(&[10.0, 12.0, 15.0]);
+        let rates: Vec<f64> = [0, 1, 2, 3, 4, 100].iter().map(|level| curve.tick_rate(*level)).collect();
+        assert_eq!(rates, vec![10.0, 10.0, 12.0, 15.0, 15.0, 15.0]);
+    }
+
+    #[test]
+    fn validate_accepts_the_default_curves() {
+        let curves = SpeedCurves::default();
+        for difficulty in Difficulty::ALL.iter() {
+            assert_eq!(curves.get(*difficulty).validate(1.0, 60.0), Ok(()));
+        }
+    }
+
+    #[test]
+    fn validate_rejects_bad_curves() {
+        assert!(curve(&[]).validate(1.0, 60.0).is_err());
+        assert!(SpeedCurve { food_per_level: 0, ..curve(&[10.0]) }.validate(1.0, 60.0).is_err());
+        assert!(curve(&[10.0, 61.0]).validate(1.0, 60.0).is_err());
+        assert!(curve(&[0.5, 10.0]).validate(1.0, 60.0).is_err());
+        assert!(curve(&[10.0, f64::NAN]).validate(1.0, 60.0).is_err());
+        assert!(curve(&[10.0, 14.0, 12.0]).validate(1.0, 60.0).is_err());
+        assert_eq!(curve(&[10.0, 10.0, 12.0]).validate(1.0, 60.0), Ok(()));
+    }
+}
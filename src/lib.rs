@@ -0,0 +1,35 @@
+//! Game rules for Rust Snake, kept free of any windowing or rendering code so they can be
+//! reused by other front-ends and tested headlessly.
+
+pub mod ai;
+pub mod batch;
+pub mod bindings;
+pub mod battlesnake;
+pub mod config;
+pub mod controller;
+pub mod env;
+pub mod level;
+pub mod map;
+pub mod net;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod replay;
+pub mod save;
+pub mod score;
+pub mod screen;
+pub mod server;
+pub mod timestep;
+pub mod world;
+
+pub use crate::batch::{BatchSummary, GameResult};
+pub use crate::bindings::{Action, Bindings};
+pub use crate::config::Config;
+pub use crate::controller::{Controller, KeyboardController};
+pub use crate::env::{Env, EnvOptions, Observation, ObservationKind, Rewards, StepResult, VecEnv};
+pub use crate::level::{Difficulty, SpeedCurve, SpeedCurves};
+pub use crate::map::Map;
+pub use crate::replay::{Replay, ReplayController, ReplayInput};
+pub use crate::save::SavedGame;
+pub use crate::score::{HighScore, HighScoreTable, Score};
+pub use crate::timestep::FixedTimestep;
+pub use crate::world::{BoundaryMode, DeathCause, MovementDirection, Snake, World, WorldView};
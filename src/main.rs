@@ -1,168 +1,411 @@
-use std::io::Empty;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
 
-use graphics::math::rotate_radians;
 use piston_window::*;
 use piston_window::types::Color;
-use rand::{Rng, RngCore, thread_rng};
-use rand::rngs::ThreadRng;
+
+use rust_snake::bindings::Bindings;
+use rust_snake::config::MAX_PLAYERS;
+use rust_snake::controller::{self, DEFAULT_BOT};
+use rust_snake::screen::{MenuItem, Screen, Screens};
+use rust_snake::{
+    Action, Config, Controller, FixedTimestep, HighScore, HighScoreTable, KeyboardController, MovementDirection, Replay,
+    ReplayController, SavedGame, World,
+};
 
 const WINDOW_TITLE: &str = "Rust Snake";
-const WINDOW_WIDTH_PIXELS: f64 = 640.0;
-const WINDOW_HEIGHT_PIXELS: f64 = 480.0;
 
-const TILE_SIZE: f64 = 20.0;
-const ROW_COUNT: usize = WINDOW_HEIGHT_PIXELS as usize / TILE_SIZE as usize;
-const COL_COUNT: usize = WINDOW_WIDTH_PIXELS as usize / TILE_SIZE as usize;
+const FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono.ttf");
+const FONT_SIZE: u32 = 16;
+const LINE_HEIGHT: f64 = 22.0;
+
+/// The strip above the board that holds the HUD, tall enough for two lines of text
+const HUD_HEIGHT: f64 = LINE_HEIGHT * 2.0 + 8.0;
+const HUD_PADDING: f64 = 8.0;
+
+/// How often the FPS counter is updated
+const FPS_INTERVAL: Duration = Duration::from_millis(500);
 
 const COLOR_WHITE: Color = [200.0, 200.0, 200.0, 1.0];
 const COLOR_RED: Color = [200.0, 0.0, 0.0, 1.0];
 const COLOR_GREEN: Color = [0.0, 200.0, 0.0, 1.0];
+const COLOR_BLUE: Color = [0.1, 0.3, 0.9, 1.0];
+const COLOR_ORANGE: Color = [1.0, 0.55, 0.0, 1.0];
+const COLOR_PURPLE: Color = [0.55, 0.2, 0.75, 1.0];
+const COLOR_GREY: Color = [0.4, 0.4, 0.4, 1.0];
+const COLOR_OVERLAY: Color = [0.0, 0.0, 0.0, 0.75];
+const COLOR_DARK_GREY: Color = [0.2, 0.2, 0.2, 1.0];
+const COLOR_BLACK: Color = [0.1, 0.1, 0.1, 1.0];
 
 const COLOR_EMPTY: Color = COLOR_WHITE;
 const COLOR_FOOD: Color = COLOR_GREEN;
-const COLOR_SNAKE: Color = COLOR_RED;
-
-const FRAME_PER_SECONDS: u128 = 30;
-const MILLIS_PER_FRAME: u128 = (1000.0 / FRAME_PER_SECONDS as f64) as u128;
-
-#[derive(Debug)]
-enum MovementDirection {
-    Up,
-    Left,
-    Down,
-    Right,
-}
-
-#[derive(Debug)]
-struct World {
-    pub is_running: bool,
-    pub row_count: usize,
-    pub col_count: usize,
-    pub rng: ThreadRng,
-    pub movement_direction: MovementDirection,
-    pub snake_body: Vec<[usize; 2]>,
-}
-
-impl World {
-    pub fn new(rows: usize, cols: usize) -> World {
-        let mut world = World {
-            is_running: true,
-            row_count: rows,
-            col_count: cols,
-            rng: thread_rng(),
-            movement_direction: MovementDirection::Up,
-            snake_body: Vec::new(),
-        };
-
-        world.init();
-        world
-    }
-
-    pub fn init(&mut self) {
-        self.movement_direction = MovementDirection::Left;
-        self.snake_body.push([0, 0]);
-        self.snake_body.push([0, 1]);
-        self.snake_body.push([0, 2]);
-    }
-
-    pub fn step(&mut self) {
-        if !self.is_running {
-            return;
-        }
+const COLOR_OBSTACLE: Color = COLOR_DARK_GREY;
+/// One colour per player, in player order
+const COLOR_SNAKES: [Color; MAX_PLAYERS] = [COLOR_RED, COLOR_BLUE, COLOR_ORANGE, COLOR_PURPLE];
+const COLOR_DEAD_SNAKE: Color = COLOR_GREY;
+const COLOR_TEXT: Color = COLOR_WHITE;
+const COLOR_SELECTED_TEXT: Color = COLOR_ORANGE;
+const COLOR_TITLE_BACKGROUND: Color = COLOR_BLACK;
+const COLOR_HUD_BACKGROUND: Color = COLOR_BLACK;
+const COLOR_BANNER_TEXT: Color = COLOR_ORANGE;
+
+/// How many players can share the keyboard, player 1 with the bound keys and player 2 with
+/// the first preset that does not clash with them
+const KEYBOARD_PLAYERS: usize = 2;
+
+/// How much the + and - keys speed up or slow down the game, as a factor
+const SPEED_MULTIPLIER_STEP: f64 = 1.25;
+/// How far the + and - keys can go, so that a few presses always undo a run of presses the other way
+const MIN_SPEED_MULTIPLIER: f64 = 1.0 / 16.0;
+const MAX_SPEED_MULTIPLIER: f64 = 16.0;
 
-        let len = self.snake_body.len();
-
-        for i in 0..len - 1 {
-            self.snake_body[i][0] = self.snake_body[i + 1][0];
-            self.snake_body[i][1] = self.snake_body[i + 1][1];
-        }
-
-        if let Some(last) = self.snake_body.last_mut() {
-            let wrap = |n: i32, max: u32| {
-                let max = max as i32;
-                if n >= 0 {
-                    n % max
-                } else {
-                    (max + (n % max)) % max
-                }
-            };
-
-            match self.movement_direction {
-                MovementDirection::Up => { last[0] = wrap(last[0] as i32 - 1, self.row_count as u32) as usize }
-                MovementDirection::Left => { last[1] = wrap(last[1] as i32 - 1, self.col_count as u32) as usize }
-                MovementDirection::Down => { last[0] = wrap(last[0] as i32 + 1, self.row_count as u32) as usize }
-                MovementDirection::Right => { last[1] = wrap(last[1] as i32 + 1, self.col_count as u32) as usize }
-            }
+fn main() {
+    let config = Config::from_args(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("{}", rust_snake::config::USAGE);
+        process::exit(2);
+    });
+
+    // In replay mode the game is played with the settings it was recorded with
+    let replay = config.replay_path.as_ref().map(|path| {
+        Replay::load(path).unwrap_or_else(|error| {
+            eprintln!("Could not read replay from {}: {}", path.display(), error);
+            process::exit(1);
+        })
+    });
+    let config = match &replay {
+        Some(replay) => Config {
+            tile_size: config.tile_size,
+            show_fps: config.show_fps,
+            bindings: config.bindings.clone(),
+            ..replay.config.clone()
+        },
+        None => config,
+    };
+    let mut replay_controllers = replay.as_ref().map(ReplayController::for_players);
+
+    // Otherwise pick up the game that was in progress when the window was last closed
+    let mut save_path = SavedGame::default_path().filter(|_| replay_controllers.is_none());
+    let saved_game = match save_path.as_ref().filter(|_| !config.new_game).map(|path| SavedGame::load(path)) {
+        Some(Ok(saved_game)) => saved_game,
+        Some(Err(error)) => {
+            // An unreadable save is left alone, rather than overwritten or removed on quit
+            let path = save_path.take().unwrap();
+            eprintln!("Could not resume the game saved in {}: {}; it will be left as it is", path.display(), error);
+            None
         }
+        None => None,
+    };
+    let config = match &saved_game {
+        Some(saved_game) => Config {
+            tile_size: config.tile_size,
+            show_fps: config.show_fps,
+            bindings: config.bindings.clone(),
+            record_path: config.record_path.clone(),
+            ..saved_game.config.clone()
+        },
+        None => config,
+    };
+
+    // Key names can only be checked against the keys piston knows about
+    if let Err(error) = config.bindings.validate(is_key_name) {
+        eprintln!("{}", error);
+        process::exit(2);
     }
-}
 
-fn main() {
     let mut window: PistonWindow<> =
         WindowSettings::new(
             WINDOW_TITLE,
-            [WINDOW_WIDTH_PIXELS, WINDOW_HEIGHT_PIXELS], )
-            .exit_on_esc(true)
+            [config.window_size()[0], config.window_size()[1] + HUD_HEIGHT], )
             .build()
             .unwrap();
 
-    let mut world: World = World::new(20, 20);
+    let mut glyphs = Glyphs::from_bytes(FONT, window.create_texture_context(), TextureSettings::new()).unwrap();
+
+    // Replays start playing straight away, a resumed game starts paused, and anything else on
+    // the title screen
+    let first_screen = if replay_controllers.is_some() {
+        Screen::Playing
+    } else if saved_game.is_some() {
+        Screen::Paused
+    } else {
+        Screen::Title
+    };
+    let mut screens = Screens::new(first_screen, replay_controllers.is_some());
+
+    let (mut world, mut recording) = match saved_game {
+        Some(saved_game) => {
+            eprintln!("Resumed the saved game (start with --new-game to discard it)");
+            let mut world = saved_game.world;
+            world.is_running = false;
+            (world, saved_game.replay)
+        }
+        None => {
+            let world = World::from_config(&config);
+            let recording = Replay::new(&config, &world);
+            (world, recording)
+        }
+    };
+
+    // An unreadable high-score file is left alone, rather than overwritten with this session's scores
+    let (mut high_scores, high_score_path) = HighScoreTable::load_default().unwrap_or_else(|error| {
+        eprintln!("{}; high scores will not be saved this session", error);
+        (HighScoreTable::default(), None)
+    });
+
+    // The rank of the finished game in the high-score table, once it has been recorded
+    let mut recorded_rank: Option<Option<usize>> = None;
+
+    // Each player steers with their own keys, unless a bot plays for them
+    let player_bindings: Vec<Bindings> = Some(config.bindings.clone()).into_iter()
+        .chain(config.bindings.second_player())
+        .take(KEYBOARD_PLAYERS)
+        .collect();
+    let mut keyboards = vec![KeyboardController::new(); config.players];
+    let mut bots = player_bots(&config, player_bindings.len(), world.seed);
+
+    // The direction each gamepad axis was last pushed in, so that holding a stick steers once
+    let mut axis_directions = HashMap::new();
+    let mut gamepads = Gamepads::new();
+    let mut pending = VecDeque::new();
+
+    // The settings screen edits these, and the next new game takes them up
+    let mut settings = config.clone();
+    let mut config = config;
+
+    // Time spent playing the current game, not counting pauses
+    let mut elapsed = Duration::ZERO;
+    let mut fps_counter = FpsCounter::new();
 
     // MAIN LOOP
-    let mut previous_update = UNIX_EPOCH;
-    while let Some(event) = window.next() {
-        if let Some(key) = event.press_args() {
-            if key == Button::Keyboard(Key::Up) {
-                world.movement_direction = MovementDirection::Up
+    let mut speed_multiplier = 1.0;
+    let mut level = config.speed_curve().level(world.max_food_eaten());
+    let mut timestep = FixedTimestep::new(config.tick_rate(level));
+    while let Some(event) = pending.pop_front().or_else(|| window.next()) {
+        gamepads.poll(&mut pending);
+        let mut is_new_game = false;
+
+        let key = match event.press_args() {
+            Some(Button::Keyboard(key)) => Some(key),
+            _ => None,
+        };
+        let actions = input_actions(&event, &player_bindings, &mut axis_directions);
+        let is_action = |action: Action| actions.contains(&(0, action));
+        let is_select = key == Some(Key::Return) || key == Some(Key::Space) || match event.press_args() {
+            Some(Button::Controller(button)) => player_bindings[0].gamepad.select.contains(&button.button),
+            _ => false,
+        };
+
+        match screens.screen() {
+            Screen::Playing => {
+                if replay_controllers.is_none() {
+                    for (i_player, action) in actions.iter() {
+                        if let (Some(keyboard), Some(direction)) = (keyboards.get_mut(*i_player), action.direction()) {
+                            keyboard.press(direction);
+                        }
+                    }
+                }
+
+                if is_action(Action::Pause) {
+                    screens.go_to(Screen::Paused);
+                } else if is_action(Action::Restart) {
+                    is_new_game = true;
+                } else if is_action(Action::Quit) {
+                    window.set_should_close(true);
+                }
+            }
+            Screen::GameOver => {
+                if is_select || is_action(Action::Restart) {
+                    is_new_game = true;
+                } else if key == Some(Key::Escape) {
+                    screens.go_to(Screen::Title);
+                } else if is_action(Action::Quit) {
+                    window.set_should_close(true);
+                }
+            }
+            Screen::Title | Screen::Paused | Screen::Settings { .. } => {
+                // The arrow keys, Return and Esc always work in menus, alongside the bindings
+                let is_paused = screens.screen() == Screen::Paused;
+                if is_select {
+                    match screens.selected_item() {
+                        Some(MenuItem::NewGame) | Some(MenuItem::Restart) => is_new_game = true,
+                        Some(MenuItem::Resume) => screens.go_to(Screen::Playing),
+                        Some(MenuItem::Settings) => screens.go_to(Screen::Settings { from_pause: is_paused }),
+                        Some(MenuItem::Title) => screens.go_to(Screen::Title),
+                        Some(MenuItem::Quit) => window.set_should_close(true),
+                        Some(MenuItem::Setting(setting)) => setting.change(&mut settings, true),
+                        Some(MenuItem::Back) | None => {
+                            if let Some(screen) = screens.back() {
+                                screens.go_to(screen);
+                            }
+                        }
+                    }
+                } else if key == Some(Key::Escape) || (is_paused && is_action(Action::Pause)) {
+                    match screens.back() {
+                        Some(screen) => screens.go_to(screen),
+                        None => window.set_should_close(true),
+                    }
+                } else if key == Some(Key::Up) || is_action(Action::Up) {
+                    screens.move_selection(false);
+                } else if key == Some(Key::Down) || is_action(Action::Down) {
+                    screens.move_selection(true);
+                } else if key == Some(Key::Left) || key == Some(Key::Right) || is_action(Action::Left) || is_action(Action::Right) {
+                    if let Some(MenuItem::Setting(setting)) = screens.selected_item() {
+                        setting.change(&mut settings, key == Some(Key::Right) || is_action(Action::Right));
+                    }
+                } else if is_paused && is_action(Action::Restart) {
+                    is_new_game = true;
+                } else if is_action(Action::Quit) {
+                    window.set_should_close(true);
+                } else if is_paused && key == Some(Key::Period) {
+                    // Step a paused replay forward by a single tick
+                    if let Some(controllers) = &mut replay_controllers {
+                        world.is_running = true;
+                        world.step_with(&mut replay_steering(controllers));
+                        world.is_running = false;
+                    }
+                }
+            }
+        }
+
+        if screens.screen() == Screen::Playing || screens.screen() == Screen::Paused {
+            if key == Some(Key::Equals) || key == Some(Key::NumPadPlus) {
+                speed_multiplier = (speed_multiplier * SPEED_MULTIPLIER_STEP).min(MAX_SPEED_MULTIPLIER);
             }
 
-            if key == Button::Keyboard(Key::Left) {
-                world.movement_direction = MovementDirection::Left
+            if key == Some(Key::Minus) || key == Some(Key::NumPadMinus) {
+                speed_multiplier = (speed_multiplier / SPEED_MULTIPLIER_STEP).max(MIN_SPEED_MULTIPLIER);
             }
+        }
 
-            if key == Button::Keyboard(Key::Down) {
-                world.movement_direction = MovementDirection::Down
+        if is_new_game {
+            match &mut replay_controllers {
+                Some(controllers) => {
+                    // Every controller plays back the same replay, so one fresh world serves them all
+                    world = controllers[0].replay.world();
+                    controllers.iter_mut().for_each(ReplayController::rewind);
+                }
+                None => {
+                    if settings != config {
+                        config = settings.clone();
+                        world = World::from_config(&config);
+                        keyboards = vec![KeyboardController::new(); config.players];
+                        bots = player_bots(&config, player_bindings.len(), world.seed);
+                    } else if world.tick > 0 || world.is_game_over() {
+                        world.restart();
+                    }
+                    keyboards.iter_mut().for_each(KeyboardController::clear);
+                    recording = Replay::new(&config, &world);
+                }
             }
+            recorded_rank = None;
+            elapsed = Duration::ZERO;
+            timestep.reset();
+            screens.go_to(Screen::Playing);
+        }
 
-            if key == Button::Keyboard(Key::Right) {
-                world.movement_direction = MovementDirection::Right
+        // Simulation ticks are paced by a monotonic clock on update events, independently of
+        // how often or how slowly frames are rendered
+        if let Some(args) = event.update_args() {
+            world.is_running = screens.screen() == Screen::Playing;
+            if world.is_running && !world.is_game_over() {
+                elapsed += Duration::from_secs_f64(args.dt);
             }
 
-            if key == Button::Keyboard(Key::Space) {
-                world.is_running = !world.is_running
+            level = config.speed_curve().level(world.max_food_eaten());
+            timestep.set_tick_rate(config.scaled_tick_rate(level, speed_multiplier));
+
+            for _ in 0..timestep.update(Instant::now()) {
+                match &mut replay_controllers {
+                    Some(controllers) => world.step_with(&mut replay_steering(controllers)),
+                    None => recording.record_step(&mut world, &mut player_steering(&mut keyboards, &mut bots)),
+                }
             }
         }
 
-        // This part of code ensures that the program always runs at the predetermined amount of FPS rate, e.g. 60
-        if previous_update.elapsed().map(|d| d.as_millis()).unwrap_or(0) > MILLIS_PER_FRAME {
-            world.step();
-            previous_update = SystemTime::now();
+        // Replays neither count towards the high scores nor get recorded again
+        if world.is_game_over() && recorded_rank.is_none() {
+            if replay_controllers.is_some() {
+                recorded_rank = Some(None);
+            } else {
+                // In a match only the winner's score counts
+                let scorer = if world.snakes.len() > 1 { world.winner() } else { Some(0) };
+                let rank = scorer.and_then(|i| {
+                    high_scores.insert(&HighScoreTable::mode_key(&config), HighScore::from_snake(&world.snakes[i]))
+                });
+                if let Some(path) = &high_score_path {
+                    if let Err(error) = high_scores.save(path) {
+                        eprintln!("Could not save high scores to {}: {}", path.display(), error);
+                    }
+                }
+
+                if let Some(path) = &config.record_path {
+                    if let Err(error) = recording.save(path) {
+                        eprintln!("Could not save replay to {}: {}", path.display(), error);
+                    }
+                }
+
+                recorded_rank = Some(rank);
+            }
+
+            screens.go_to(Screen::GameOver);
         }
 
+        let menu_labels: Vec<String> = screens.items().iter().map(|item| item.label(&settings)).collect();
         let tile_rect = Rectangle::new(COLOR_EMPTY);
         let tile_border_rect = Rectangle::new_border(COLOR_GREEN, 0.25);
 
 
-        window.draw_2d(&event, |context, graphics, _device| {
+        if event.render_args().is_some() {
+            fps_counter.frame(Instant::now());
+        }
+
+        let hud = Hud {
+            world: &world,
+            level,
+            tick_rate: timestep.tick_rate(),
+            elapsed,
+            fps: if config.show_fps { Some(fps_counter.fps) } else { None },
+            banner: match screens.screen() {
+                Screen::Paused => Some("PAUSED"),
+                Screen::GameOver => Some("GAME OVER"),
+                _ => None,
+            },
+        };
+
+        window.draw_2d(&event, |context, graphics, device| {
             // CLEAR SCREEN
             clear(COLOR_EMPTY, graphics);
 
+            // Scale the board to fit the window below the HUD, keeping tiles square, and center it
+            let [view_width, view_height] = context.get_view_size();
+            let board_height = (view_height - HUD_HEIGHT).max(0.0);
+            let tile_size = (view_width / world.col_count as f64).min(board_height / world.row_count as f64);
+            let offset_x = (view_width - tile_size * world.col_count as f64) / 2.0;
+            let offset_y = HUD_HEIGHT + (board_height - tile_size * world.row_count as f64) / 2.0;
+
             for i_row in 0..world.row_count {
                 for i_col in 0..world.col_count {
                     let start_coords = [
-                        i_col as f64 * TILE_SIZE,
-                        i_row as f64 * TILE_SIZE,
+                        offset_x + i_col as f64 * tile_size,
+                        offset_y + i_row as f64 * tile_size,
                     ];
 
                     let finish_coords = [
-                        (i_col + 1) as f64 * TILE_SIZE,
-                        (i_row + 1) as f64 * TILE_SIZE,
+                        offset_x + (i_col + 1) as f64 * tile_size,
+                        offset_y + (i_row + 1) as f64 * tile_size,
                     ];
 
                     let mut color = COLOR_EMPTY;
-                    if world.snake_body.contains(&[i_row, i_col]) {
-                        color = COLOR_SNAKE;
+                    if let Some(i) = world.snakes.iter().position(|snake| snake.body.contains(&[i_row, i_col])) {
+                        let snake = &world.snakes[i];
+                        color = if snake.is_alive() { COLOR_SNAKES[i] } else { COLOR_DEAD_SNAKE };
+                    } else if world.food == Some([i_row, i_col]) {
+                        color = COLOR_FOOD;
+                    } else if world.is_obstacle([i_row, i_col]) {
+                        color = COLOR_OBSTACLE;
                     }
 
                     tile_rect.color(color)
@@ -184,6 +427,407 @@ fn main() {
                         )
                 }
             }
+
+            hud.draw(&mut glyphs, &context, graphics);
+
+            let screen = screens.screen();
+            let selected = screens.selected();
+            match screen {
+                Screen::Playing => {}
+                Screen::GameOver => {
+                    if let Some(rank) = recorded_rank {
+                        let key = HighScoreTable::mode_key(&config);
+                        draw_game_over(&world, &key, high_scores.scores(&key), rank, &mut glyphs, &context, graphics);
+                    }
+                }
+                Screen::Title => {
+                    let heading = [WINDOW_TITLE];
+                    let footer = "Up and Down to choose, Enter to select";
+                    rectangle(COLOR_TITLE_BACKGROUND, [0.0, 0.0, view_width, view_height], context.transform, graphics);
+                    draw_menu(&heading, &menu_labels, selected, footer, &mut glyphs, &context, graphics);
+                }
+                Screen::Paused => {
+                    let footer = if replay_controllers.is_some() { ". steps one tick, Esc resumes" } else { "Esc resumes" };
+                    draw_menu(&["PAUSED"], &menu_labels, selected, footer, &mut glyphs, &context, graphics);
+                }
+                Screen::Settings { .. } => {
+                    let heading = ["SETTINGS", "Changes apply to the next game"];
+                    let footer = "Left and Right to change, Esc to go back";
+                    draw_menu(&heading, &menu_labels, selected, footer, &mut glyphs, &context, graphics);
+                }
+            }
+            glyphs.factory.encoder.flush(device);
+        });
+    }
+
+    // Keep an unfinished game for the next launch, and make sure a finished or unstarted one is
+    // not resumed
+    if let Some(path) = &save_path {
+        let result = if world.is_game_over() || world.tick == 0 {
+            SavedGame::remove(path)
+        } else {
+            SavedGame::new(&config, &world, &recording).save(path)
+        };
+
+        if let Err(error) = result {
+            eprintln!("Could not save the game to {}: {}", path.display(), error);
+        }
+    }
+}
+
+/// Counts rendered frames and works out the frame rate every `FPS_INTERVAL`
+struct FpsCounter {
+    frames: u32,
+    since: Instant,
+    fps: f64,
+}
+
+impl FpsCounter {
+    fn new() -> FpsCounter {
+        FpsCounter { frames: 0, since: Instant::now(), fps: 0.0 }
+    }
+
+    fn frame(&mut self, now: Instant) {
+        self.frames += 1;
+        let interval = now.duration_since(self.since);
+        if interval >= FPS_INTERVAL {
+            self.fps = self.frames as f64 / interval.as_secs_f64();
+            self.frames = 0;
+            self.since = now;
+        }
+    }
+}
+
+/// Reads gamepads with gilrs, since the window reports no controller events of its own, and
+/// hands them on as piston events numbered the way SDL numbers buttons and axes
+#[cfg(feature = "gamepad")]
+struct Gamepads {
+    gilrs: Option<gilrs::Gilrs>,
+}
+
+#[cfg(feature = "gamepad")]
+impl Gamepads {
+    fn new() -> Gamepads {
+        let gilrs = gilrs::Gilrs::new()
+            .map_err(|error| eprintln!("Could not read gamepads: {}", error))
+            .ok();
+        Gamepads { gilrs }
+    }
+
+    /// Queues an event for each gamepad button or axis that changed since the last poll
+    fn poll(&mut self, events: &mut VecDeque<Event>) {
+        let gilrs = match self.gilrs.as_mut() {
+            Some(gilrs) => gilrs,
+            None => return,
+        };
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            let id = usize::from(id) as u32;
+            let (state, button) = match event {
+                gilrs::EventType::ButtonPressed(button, _) => (ButtonState::Press, button),
+                gilrs::EventType::ButtonReleased(button, _) => (ButtonState::Release, button),
+                gilrs::EventType::AxisChanged(axis, position, _) => {
+                    if let Some((axis, sign)) = sdl_axis(axis) {
+                        events.push_back(Event::from(ControllerAxisArgs::new(id, axis, sign * position as f64)));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(button) = sdl_button(button) {
+                let button = Button::Controller(ControllerButton::new(id, button));
+                events.push_back(Event::from(ButtonArgs { state, button, scancode: None }));
+            }
+        }
+    }
+}
+
+/// Without the `gamepad` feature there are no gamepads to read
+#[cfg(not(feature = "gamepad"))]
+struct Gamepads;
+
+#[cfg(not(feature = "gamepad"))]
+impl Gamepads {
+    fn new() -> Gamepads {
+        Gamepads
+    }
+
+    fn poll(&mut self, _events: &mut VecDeque<Event>) {}
+}
+
+/// The SDL game controller number of a gilrs button, which is what the bindings use
+#[cfg(feature = "gamepad")]
+fn sdl_button(button: gilrs::Button) -> Option<u8> {
+    use gilrs::Button::*;
+    match button {
+        South => Some(0),
+        East => Some(1),
+        West => Some(2),
+        North => Some(3),
+        Select => Some(4),
+        Mode => Some(5),
+        Start => Some(6),
+        LeftThumb => Some(7),
+        RightThumb => Some(8),
+        LeftTrigger => Some(9),
+        RightTrigger => Some(10),
+        DPadUp => Some(11),
+        DPadDown => Some(12),
+        DPadLeft => Some(13),
+        DPadRight => Some(14),
+        _ => None,
+    }
+}
+
+/// The SDL game controller number of a gilrs axis, and the sign that turns gilrs' upward Y
+/// axes into SDL's downward ones
+#[cfg(feature = "gamepad")]
+fn sdl_axis(axis: gilrs::Axis) -> Option<(u8, f64)> {
+    use gilrs::Axis::*;
+    match axis {
+        LeftStickX => Some((0, 1.0)),
+        LeftStickY => Some((1, -1.0)),
+        RightStickX => Some((2, 1.0)),
+        RightStickY => Some((3, -1.0)),
+        LeftZ => Some((4, 1.0)),
+        RightZ => Some((5, 1.0)),
+        _ => None,
+    }
+}
+
+/// What the strip above the board shows: each player's score and length on the first line, the
+/// pace of the game on the second, and a banner while the game is paused or over
+struct Hud<'a> {
+    world: &'a World,
+    level: usize,
+    tick_rate: f64,
+    elapsed: Duration,
+    fps: Option<f64>,
+    banner: Option<&'a str>,
+}
+
+impl<'a> Hud<'a> {
+    fn draw(&self, glyphs: &mut Glyphs, context: &Context, graphics: &mut G2d) {
+        let [view_width, _] = context.get_view_size();
+        rectangle(COLOR_HUD_BACKGROUND, [0.0, 0.0, view_width, HUD_HEIGHT], context.transform, graphics);
+
+        let scores: Vec<(String, Color)> = if self.world.snakes.len() > 1 {
+            self.world.snakes.iter()
+                .enumerate()
+                .map(|(i, snake)| {
+                    let color = if snake.is_alive() { COLOR_SNAKES[i] } else { COLOR_DEAD_SNAKE };
+                    (format!("P{} {} ({})", i + 1, snake.score.food_eaten, snake.length()), color)
+                })
+                .collect()
+        } else {
+            let snake = &self.world.snakes[0];
+            vec![(format!("Score {}  Length {}", snake.score.food_eaten, snake.length()), COLOR_TEXT)]
+        };
+
+        let seconds = self.elapsed.as_secs();
+        let mut pace = format!(
+            "Level {}  {:.0} ticks/s  {}:{:02}",
+            self.level, self.tick_rate, seconds / 60, seconds % 60,
+        );
+        if let Some(fps) = self.fps {
+            pace.push_str(&format!("  {:.0} FPS", fps));
+        }
+
+        let mut x = HUD_PADDING;
+        for (segment, color) in scores.iter() {
+            draw_text(segment, *color, [x, LINE_HEIGHT], glyphs, context, graphics);
+            x += glyphs.width(FONT_SIZE, segment).unwrap_or(0.0) + HUD_PADDING * 2.0;
+        }
+
+        // The seed reproduces the game for bug reports, and gives way to the scores on narrow boards
+        let seed = format!("Seed {}", self.world.seed);
+        let width = glyphs.width(FONT_SIZE, &seed).unwrap_or(0.0);
+        if x + width + HUD_PADDING <= view_width {
+            draw_text(&seed, COLOR_TEXT, [view_width - width - HUD_PADDING, LINE_HEIGHT], glyphs, context, graphics);
+        }
+        draw_text(&pace, COLOR_TEXT, [HUD_PADDING, LINE_HEIGHT * 2.0], glyphs, context, graphics);
+
+        if let Some(banner) = self.banner {
+            let width = glyphs.width(FONT_SIZE, banner).unwrap_or(0.0);
+            draw_text(banner, COLOR_BANNER_TEXT, [view_width - width - HUD_PADDING, LINE_HEIGHT * 2.0], glyphs, context, graphics);
+        }
+    }
+}
+
+/// The bot for each player who has no keys of their own, or for everyone if `config` picks one
+fn player_bots(config: &Config, keyboard_players: usize, seed: u64) -> Vec<Option<Box<dyn Controller>>> {
+    (0..config.players)
+        .map(|i_player| {
+            let name = config.ai.as_deref().or(if i_player < keyboard_players { None } else { Some(DEFAULT_BOT) });
+            name.map(|name| controller::controller_by_name(name, seed.wrapping_add(i_player as u64)).unwrap())
+        })
+        .collect()
+}
+
+/// The actions `event` triggers, as `(player, action)`. Every player steers with their own
+/// keys, while the other actions and the gamepad belong to player 1.
+fn input_actions(
+    event: &Event,
+    player_bindings: &[Bindings],
+    axis_directions: &mut HashMap<(u32, u8), MovementDirection>,
+) -> Vec<(usize, Action)> {
+    let gamepad = &player_bindings[0].gamepad;
+    match (event.press_args(), event.controller_axis_args()) {
+        (Some(Button::Keyboard(key)), _) => {
+            let name = format!("{:?}", key);
+            player_bindings.iter()
+                .enumerate()
+                .flat_map(|(i_player, bindings)| {
+                    bindings.actions_for_key(&name).into_iter()
+                        .filter(move |action| i_player == 0 || action.direction().is_some())
+                        .map(move |action| (i_player, action))
+                })
+                .collect()
+        }
+        (Some(Button::Controller(button)), _) => gamepad.actions_for_button(button.button).into_iter()
+            .map(|action| (0, action))
+            .collect(),
+        (Some(Button::Hat(hat)), _) => {
+            let direction = match hat.state {
+                HatState::Up => Some(MovementDirection::Up),
+                HatState::Down => Some(MovementDirection::Down),
+                HatState::Left => Some(MovementDirection::Left),
+                HatState::Right => Some(MovementDirection::Right),
+                _ => None,
+            };
+            direction.map(|direction| (0, Action::from(direction))).into_iter().collect()
+        }
+        (_, Some(args)) => {
+            let direction = gamepad.axis_direction(args.axis, args.position);
+            let previous = match direction {
+                Some(direction) => axis_directions.insert((args.id, args.axis), direction),
+                None => axis_directions.remove(&(args.id, args.axis)),
+            };
+            direction.filter(|direction| previous != Some(*direction))
+                .map(|direction| (0, Action::from(direction)))
+                .into_iter()
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Whether piston knows a key called `name`, in any case
+fn is_key_name(name: &str) -> bool {
+    (0x00..0x80).chain(0x4000_0039..=0x4000_011A)
+        .map(Key::from)
+        .filter(|key| *key != Key::Unknown)
+        .any(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+}
+
+/// Pairs each snake with whatever steers it: its bot if it has one, otherwise its keys
+fn player_steering<'a>(
+    keyboards: &'a mut [KeyboardController],
+    bots: &'a mut [Option<Box<dyn Controller>>],
+) -> Vec<&'a mut dyn Controller> {
+    keyboards.iter_mut()
+        .zip(bots.iter_mut())
+        .map(|(keyboard, bot)| match bot {
+            Some(bot) => bot.as_mut() as &mut dyn Controller,
+            None => keyboard as &mut dyn Controller,
+        })
+        .collect()
+}
+
+fn replay_steering(controllers: &mut [ReplayController]) -> Vec<&mut dyn Controller> {
+    controllers.iter_mut().map(|controller| controller as &mut dyn Controller).collect()
+}
+
+/// Dims the board and lists the final score along with the high scores for this mode
+fn draw_game_over(
+    world: &World,
+    mode_key: &str,
+    high_scores: &[HighScore],
+    rank: Option<usize>,
+    glyphs: &mut Glyphs,
+    context: &Context,
+    graphics: &mut G2d,
+) {
+    draw_overlay(context, graphics);
+
+    let mut lines = vec![String::from("GAME OVER")];
+    if world.snakes.len() > 1 {
+        lines.push(match world.winner() {
+            Some(i) => format!("Player {} wins", i + 1),
+            None => String::from("Draw"),
         });
+        for (i, snake) in world.snakes.iter().enumerate() {
+            lines.push(format!(
+                "Player {}: Food: {}  Length: {}  Ticks: {}{}",
+                i + 1,
+                snake.score.food_eaten,
+                snake.length(),
+                snake.score.ticks,
+                snake.death_cause.map(|cause| format!("  ({})", cause)).unwrap_or_default(),
+            ));
+        }
+    } else {
+        let snake = &world.snakes[0];
+        lines.push(format!("Food: {}  Length: {}  Ticks: {}", snake.score.food_eaten, snake.length(), snake.score.ticks));
+    }
+    lines.push(String::new());
+    lines.push(format!("High scores ({})", mode_key));
+
+    for (i, high_score) in high_scores.iter().enumerate() {
+        let marker = if rank == Some(i) { ">" } else { " " };
+        lines.push(format!(
+            "{}{:>2}. {:>4} food  {:>4} long  {:>6} ticks",
+            marker, i + 1, high_score.food_eaten, high_score.length, high_score.ticks,
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push(String::from("Press Return to play again, Esc for the title screen"));
+
+    for (i, line) in lines.iter().enumerate() {
+        draw_line(line, COLOR_TEXT, i, glyphs, context, graphics);
+    }
+}
+
+/// Dims the board and lists the heading, the menu with the selected item marked, and a hint
+/// about the keys
+fn draw_menu(
+    heading: &[&str],
+    items: &[String],
+    selected: usize,
+    footer: &str,
+    glyphs: &mut Glyphs,
+    context: &Context,
+    graphics: &mut G2d,
+) {
+    draw_overlay(context, graphics);
+
+    for (i, line) in heading.iter().enumerate() {
+        draw_line(line, COLOR_TEXT, i, glyphs, context, graphics);
     }
+
+    let first_item = heading.len() + 1;
+    for (i, item) in items.iter().enumerate() {
+        let (marker, color) = if i == selected { (">", COLOR_SELECTED_TEXT) } else { (" ", COLOR_TEXT) };
+        draw_line(&format!("{} {}", marker, item), color, first_item + i, glyphs, context, graphics);
+    }
+
+    draw_line(footer, COLOR_TEXT, first_item + items.len() + 1, glyphs, context, graphics);
+}
+
+/// Dims everything below the HUD, which stays readable
+fn draw_overlay(context: &Context, graphics: &mut G2d) {
+    let [view_width, view_height] = context.get_view_size();
+    rectangle(COLOR_OVERLAY, [0.0, HUD_HEIGHT, view_width, view_height - HUD_HEIGHT], context.transform, graphics);
+}
+
+/// Draws one line of text on the `i`th line of an overlay
+fn draw_line(line: &str, color: Color, i: usize, glyphs: &mut Glyphs, context: &Context, graphics: &mut G2d) {
+    draw_text(line, color, [LINE_HEIGHT, HUD_HEIGHT + LINE_HEIGHT * (i + 1) as f64], glyphs, context, graphics);
+}
+
+/// Draws `text` with its baseline starting at `position`
+fn draw_text(text: &str, color: Color, position: [f64; 2], glyphs: &mut Glyphs, context: &Context, graphics: &mut G2d) {
+    text::Text::new_color(color, FONT_SIZE)
+        .draw(text, glyphs, &context.draw_state, context.transform.trans(position[0], position[1]), graphics)
+        .unwrap();
 }
@@ -0,0 +1,305 @@
+//! Level maps: walls, where each snake starts, and where food may appear, drawn as ASCII art.
+//!
+//! ```text
+//! ; Lines starting with a semicolon are comments
+//! ##########
+//! #..>.....#
+//! #...**...#
+//! #.....<..#
+//! ##########
+//! ```
+//!
+//! `#` is a wall and `.` is open floor. `*` is floor where food may appear; a map without any
+//! lets food appear on any free tile. `>`, `<`, `^` and `v` mark the head of a starting snake
+//! and the way it is heading, with the rest of its body trailing behind on open floor. Players
+//! take the starting positions in reading order.
+
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::world::MovementDirection;
+
+/// Length of the snakes at the start of a game, on a map or not
+pub const START_LENGTH: usize = 3;
+
+/// Maps that ship with the game, as `(name, contents)`
+pub const BUILT_IN_MAPS: [(&str, &str); 4] = [
+    ("box", include_str!("../assets/maps/box.txt")),
+    ("cross", include_str!("../assets/maps/cross.txt")),
+    ("rooms", include_str!("../assets/maps/rooms.txt")),
+    ("pillars", include_str!("../assets/maps/pillars.txt")),
+];
+
+/// Where and how a snake starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Start {
+    pub head: [usize; 2],
+    pub heading: MovementDirection,
+}
+
+impl Start {
+    /// The starting body, from the tail to the head
+    pub fn body(&self) -> Vec<[usize; 2]> {
+        let [row, col] = self.head;
+        (0..START_LENGTH).rev()
+            .map(|behind| match self.heading {
+                MovementDirection::Up => [row + behind, col],
+                MovementDirection::Left => [row, col + behind],
+                MovementDirection::Down => [row - behind, col],
+                MovementDirection::Right => [row, col - behind],
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "MapSource", into = "MapSource")]
+pub struct Map {
+    pub name: String,
+    pub row_count: usize,
+    pub col_count: usize,
+    pub starts: Vec<Start>,
+    /// The map as it was drawn, without comments
+    grid: Vec<String>,
+    walls: Vec<bool>,
+    food_zone: Vec<bool>,
+}
+
+/// How a map appears in configuration files, replays and save files: either the name of a
+/// built-in map or a map file, or the map itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum MapSource {
+    Named(String),
+    Inline { name: String, grid: Vec<String> },
+}
+
+impl TryFrom<MapSource> for Map {
+    type Error = String;
+
+    fn try_from(source: MapSource) -> Result<Map, String> {
+        match source {
+            MapSource::Named(name) => Map::find(&name),
+            MapSource::Inline { name, grid } => Map::parse(&name, &grid.join("\n")),
+        }
+    }
+}
+
+impl From<Map> for MapSource {
+    fn from(map: Map) -> MapSource {
+        MapSource::Inline { name: map.name, grid: map.grid }
+    }
+}
+
+impl Map {
+    /// Looks `name` up among the built-in maps, and otherwise reads it as a map file
+    pub fn find(name: &str) -> Result<Map, String> {
+        match BUILT_IN_MAPS.iter().find(|(built_in, _)| *built_in == name) {
+            Some((name, contents)) => Map::parse(name, contents),
+            None if Path::new(name).exists() => Map::load(Path::new(name)),
+            None => {
+                let names: Vec<&str> = BUILT_IN_MAPS.iter().map(|(name, _)| *name).collect();
+                Err(format!("unknown map: {} (expected one of {}, or a map file)", name, names.join(", ")))
+            }
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Map, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("could not read map {}: {}", path.display(), error))?;
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        Map::parse(&name, &contents).map_err(|error| format!("invalid map {}: {}", path.display(), error))
+    }
+
+    pub fn parse(name: &str, contents: &str) -> Result<Map, String> {
+        let grid: Vec<String> = contents.lines()
+            .map(|line| line.trim_end().to_string())
+            .filter(|line| !line.is_empty() && !line.starts_with(';'))
+            .collect();
+
+        let row_count = grid.len();
+        let col_count = grid.first().map(|line| line.chars().count()).unwrap_or(0);
+        if row_count == 0 || col_count == 0 {
+            return Err(String::from("the map is empty"));
+        }
+
+        let mut walls = vec![false; row_count * col_count];
+        let mut food_zone = vec![false; row_count * col_count];
+        let mut starts = Vec::new();
+
+        for (i_row, line) in grid.iter().enumerate() {
+            if line.chars().count() != col_count {
+                return Err(format!("row {} is {} tiles wide, expected {}", i_row + 1, line.chars().count(), col_count));
+            }
+
+            for (i_col, tile) in line.chars().enumerate() {
+                let heading = match tile {
+                    '#' => {
+                        walls[i_row * col_count + i_col] = true;
+                        continue;
+                    }
+                    '*' => {
+                        food_zone[i_row * col_count + i_col] = true;
+                        continue;
+                    }
+                    '.' => continue,
+                    '^' => MovementDirection::Up,
+                    '<' => MovementDirection::Left,
+                    'v' => MovementDirection::Down,
+                    '>' => MovementDirection::Right,
+                    _ => return Err(format!("unknown tile '{}' in row {}", tile, i_row + 1)),
+                };
+                starts.push(Start { head: [i_row, i_col], heading });
+            }
+        }
+
+        let map = Map {
+            name: name.to_string(),
+            row_count,
+            col_count,
+            starts,
+            grid,
+            walls,
+            food_zone,
+        };
+
+        let mut start_tiles = Vec::new();
+        for (i, start) in map.starts.iter().enumerate() {
+            if !map.has_room_for(start) {
+                return Err(format!("snake {} has no room for its body behind its head", i + 1));
+            }
+
+            let body = start.body();
+            if body.iter().any(|tile| start_tiles.contains(tile)) {
+                return Err(format!("snake {} starts on top of another snake", i + 1));
+            }
+            start_tiles.extend(body);
+        }
+
+        Ok(map)
+    }
+
+    /// A map with nothing but the given walls, such as the hazards of a Battlesnake board
+    pub fn with_walls(name: &str, row_count: usize, col_count: usize, walls: &[[usize; 2]]) -> Map {
+        let mut wall_grid = vec![false; row_count * col_count];
+        for wall in walls.iter().filter(|wall| wall[0] < row_count && wall[1] < col_count) {
+            wall_grid[wall[0] * col_count + wall[1]] = true;
+        }
+
+        let grid = (0..row_count)
+            .map(|i_row| (0..col_count).map(|i_col| if wall_grid[i_row * col_count + i_col] { '#' } else { '.' }).collect())
+            .collect();
+
+        Map {
+            name: name.to_string(),
+            row_count,
+            col_count,
+            starts: Vec::new(),
+            grid,
+            walls: wall_grid,
+            food_zone: vec![false; row_count * col_count],
+        }
+    }
+
+    pub fn is_wall(&self, tile: [usize; 2]) -> bool {
+        tile[0] < self.row_count && tile[1] < self.col_count && self.walls[tile[0] * self.col_count + tile[1]]
+    }
+
+    pub fn is_food_zone(&self, tile: [usize; 2]) -> bool {
+        tile[0] < self.row_count && tile[1] < self.col_count && self.food_zone[tile[0] * self.col_count + tile[1]]
+    }
+
+    pub fn has_food_zone(&self) -> bool {
+        self.food_zone.contains(&true)
+    }
+
+    /// Every wall tile, in reading order
+    pub fn walls(&self) -> Vec<[usize; 2]> {
+        (0..self.row_count)
+            .flat_map(|i_row| (0..self.col_count).map(move |i_col| [i_row, i_col]))
+            .filter(|tile| self.is_wall(*tile))
+            .collect()
+    }
+
+    fn has_room_for(&self, start: &Start) -> bool {
+        let [row, col] = start.head;
+        let behind = START_LENGTH - 1;
+        let fits = match start.heading {
+            MovementDirection::Up => row + behind < self.row_count,
+            MovementDirection::Left => col + behind < self.col_count,
+            MovementDirection::Down => row >= behind,
+            MovementDirection::Right => col >= behind,
+        };
+
+        fits && start.body().iter().all(|tile| !self.is_wall(*tile))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn built_in_maps_parse() {
+        for (name, _) in BUILT_IN_MAPS.iter() {
+            let map = Map::find(name).unwrap();
+            assert_eq!(&map.name, name);
+            assert!(map.starts.len() >= 2, "{} has {} starts", name, map.starts.len());
+            assert!(!map.walls().is_empty());
+        }
+    }
+
+    #[test]
+    fn parse_reads_walls_food_zones_and_starts() {
+        let map = Map::parse("test", "; a comment\n######\n#..>*#\n#....#\n").unwrap();
+        assert_eq!((map.row_count, map.col_count), (3, 6));
+        assert!(map.is_wall([0, 0]) && !map.is_wall([1, 2]));
+        assert!(map.is_food_zone([1, 4]) && !map.is_food_zone([1, 2]));
+        assert_eq!(map.starts, vec![Start { head: [1, 3], heading: MovementDirection::Right }]);
+        assert_eq!(map.starts[0].body(), vec![[1, 1], [1, 2], [1, 3]]);
+    }
+
+    #[test]
+    fn ragged_rows_are_refused() {
+        let error = Map::parse("test", "....\n...\n").unwrap_err();
+        assert_eq!(error, "row 2 is 3 tiles wide, expected 4");
+    }
+
+    #[test]
+    fn unknown_tiles_are_refused() {
+        let error = Map::parse("test", "....\n..x.\n").unwrap_err();
+        assert_eq!(error, "unknown tile 'x' in row 2");
+    }
+
+    #[test]
+    fn empty_maps_are_refused() {
+        assert_eq!(Map::parse("test", "; nothing but a comment\n").unwrap_err(), "the map is empty");
+    }
+
+    #[test]
+    fn starts_need_room_behind_the_head() {
+        // Off the edge of the board, and into a wall
+        assert!(Map::parse("test", ".>....\n......\n").is_err());
+        assert!(Map::parse("test", "....\n#.>.\n").is_err());
+        assert!(Map::parse("test", "..>.\n....\n").is_ok());
+    }
+
+    #[test]
+    fn overlapping_starts_are_refused() {
+        let error = Map::parse("test", ".....\n.....\n..v.>\n").unwrap_err();
+        assert_eq!(error, "snake 2 starts on top of another snake");
+    }
+
+    #[test]
+    fn a_map_without_starts_parses_but_cannot_be_played() {
+        let map = Map::parse("test", "......\n..##..\n......\n").unwrap();
+        assert!(map.starts.is_empty());
+
+        let config = Config { row_count: 3, col_count: 6, map: Some(map), ..Config::default() };
+        assert_eq!(config.validate().unwrap_err(), "the test map has room for 0 players at most");
+    }
+}
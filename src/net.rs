@@ -0,0 +1,221 @@
+//! The protocol for networked play. A server runs the only `World` and clients send it their
+//! turns over TCP. Every message is one line of JSON in either direction.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::map::Map;
+use crate::world::{BoundaryMode, MovementDirection, Snake, World, WorldView};
+
+/// Bumped whenever a change to the messages would confuse older clients or servers
+pub const PROTOCOL_VERSION: u32 = 3;
+
+pub const DEFAULT_SERVER_ADDRESS: &str = "127.0.0.1:7878";
+
+/// The longest line either side accepts, far beyond the snapshot of the largest board, so that
+/// a peer that never ends its line cannot make the other side buffer without limit
+pub const MAX_MESSAGE_LEN: u64 = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ClientMessage {
+    /// Asks for a free player slot. Must be the first message on a connection.
+    Join { version: u32, name: String },
+    /// Turns the player's snake on the next tick the server runs. `tick` is the latest snapshot
+    /// the client had seen, which tells the server how far behind the client is.
+    Input { direction: MovementDirection, tick: u64 },
+    Leave,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ServerMessage {
+    /// Accepts a join. `player` is the index of the client's snake. The map, if any, comes with
+    /// the config rather than with every snapshot.
+    Welcome { player: usize, config: Box<Config> },
+    /// Refuses a join, after which the server closes the connection
+    Rejected { reason: String },
+    Snapshot(Snapshot),
+}
+
+/// Who is playing in one player slot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerStatus {
+    /// The name the client joined with, or `None` while a bot plays in the slot
+    pub name: Option<String>,
+    /// How many ticks behind the server the client's last input was
+    pub lag_ticks: u64,
+}
+
+/// Everything a client needs to draw the game and steer its snake, sent after every tick
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Counts the games played since the server started, from 1
+    pub round: u32,
+    pub tick: u64,
+    pub is_running: bool,
+    pub row_count: usize,
+    pub col_count: usize,
+    pub boundary: BoundaryMode,
+    pub food: Option<[usize; 2]>,
+    pub snakes: Vec<Snake>,
+    pub players: Vec<PlayerStatus>,
+}
+
+impl Snapshot {
+    pub fn new(round: u32, world: &World, players: Vec<PlayerStatus>) -> Snapshot {
+        Snapshot {
+            round,
+            tick: world.tick,
+            is_running: world.is_running,
+            row_count: world.row_count,
+            col_count: world.col_count,
+            boundary: world.boundary,
+            food: world.food,
+            snakes: world.snakes.clone(),
+            players,
+        }
+    }
+
+    /// A copy of the server's world for front-ends that draw a `World`, on the map from the
+    /// server's config. It cannot be stepped like the original, since the food placement depends
+    /// on the server's random state.
+    pub fn to_world(&self, map: Option<&Map>) -> World {
+        let mut world = World::with_players(self.row_count, self.col_count, self.snakes.len(), 0);
+        world.map = map.cloned();
+        world.is_running = self.is_running;
+        world.boundary = self.boundary;
+        world.food = self.food;
+        world.snakes = self.snakes.clone();
+        world.tick = self.tick;
+        world
+    }
+
+    /// What the controller of snake `you` would see on the server, given the map from its config
+    pub fn view<'a>(&'a self, you: usize, map: Option<&'a Map>) -> WorldView<'a> {
+        let snake = &self.snakes[you];
+        WorldView {
+            snake_body: &snake.body,
+            heading: snake.movement_direction,
+            snakes: &self.snakes,
+            you,
+            food: self.food,
+            row_count: self.row_count,
+            col_count: self.col_count,
+            boundary: self.boundary,
+            tick: self.tick,
+            map,
+        }
+    }
+}
+
+/// Writes `message` as one line of JSON
+pub fn write_message<W: Write, M: Serialize>(writer: &mut W, message: &M) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+/// Reads one line of JSON, returning `None` once the other side has closed the connection.
+/// A line longer than `MAX_MESSAGE_LEN` is an error.
+pub fn read_message<R: BufRead, M: for<'de> Deserialize<'de>>(reader: &mut R) -> io::Result<Option<M>> {
+    let mut line = String::new();
+    let length = reader.by_ref().take(MAX_MESSAGE_LEN).read_line(&mut line)?;
+    if length == 0 {
+        return Ok(None);
+    }
+
+    if length as u64 == MAX_MESSAGE_LEN && !line.ends_with('\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message longer than {} bytes", MAX_MESSAGE_LEN),
+        ));
+    }
+
+    serde_json::from_str(&line)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// A client's connection to a server, after the server accepted it as a player
+#[derive(Debug)]
+pub struct Connection {
+    pub player: usize,
+    /// The settings the server plays with
+    pub config: Config,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    /// Connects to the server at `address` and joins as `name`
+    pub fn join<A: ToSocketAddrs>(address: A, name: &str) -> io::Result<Connection> {
+        let stream = TcpStream::connect(address)?;
+        // Inputs are tiny and time-critical, so they should not wait to be batched
+        stream.set_nodelay(true)?;
+
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        write_message(&mut writer, &ClientMessage::Join { version: PROTOCOL_VERSION, name: name.to_string() })?;
+
+        loop {
+            match read_message(&mut reader)? {
+                Some(ServerMessage::Welcome { player, config }) => {
+                    return Ok(Connection { player, config: *config, reader, writer });
+                }
+                Some(ServerMessage::Rejected { reason }) => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason));
+                }
+                Some(ServerMessage::Snapshot(_)) => {}
+                None => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            }
+        }
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        write_message(&mut self.writer, message)
+    }
+
+    /// Waits for the next message, returning `None` once the server has closed the connection
+    pub fn receive(&mut self) -> io::Result<Option<ServerMessage>> {
+        read_message(&mut self.reader)
+    }
+
+    /// A second handle for sending from another thread while this one blocks in `receive`
+    pub fn sender(&self) -> io::Result<TcpStream> {
+        self.writer.try_clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &ClientMessage::Input { direction: MovementDirection::Up, tick: 7 }).unwrap();
+        write_message(&mut buffer, &ClientMessage::Leave).unwrap();
+
+        let mut reader = io::Cursor::new(buffer);
+        let first: Option<ClientMessage> = read_message(&mut reader).unwrap();
+        let second: Option<ClientMessage> = read_message(&mut reader).unwrap();
+        let end: Option<ClientMessage> = read_message(&mut reader).unwrap();
+        assert_eq!(first, Some(ClientMessage::Input { direction: MovementDirection::Up, tick: 7 }));
+        assert_eq!(second, Some(ClientMessage::Leave));
+        assert_eq!(end, None);
+    }
+
+    #[test]
+    fn overlong_messages_are_rejected() {
+        // A line that never ends, as from a misbehaving peer
+        let mut reader = io::Cursor::new(vec![b' '; MAX_MESSAGE_LEN as usize + 10]);
+        let result: io::Result<Option<ClientMessage>> = read_message(&mut reader);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
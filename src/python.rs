@@ -0,0 +1,401 @@
+//! A Python extension module, built with the `python` feature. Build and install it into the
+//! current virtualenv with `maturin develop --release`, or without maturin:
+//!
+//! ```text
+//! cargo rustc --release --lib --crate-type cdylib --no-default-features --features python
+//! cp target/release/librust_snake.so rust_snake.so
+//! python3 -c "import rust_snake; print(rust_snake.World(seed=1).state())"
+//! ```
+//!
+//! Keyword arguments to `World`, `Env` and `VecEnv` are the command-line options without their
+//! dashes, so `World(rows=10, boundary="walls", map="box")` plays like
+//! `--rows 10 --boundary walls --map box`. Actions are indices into `ACTIONS`: up, left, down
+//! and right. Rendering needs NumPy.
+
+// The code generated by `#[pymethods]` converts every `PyResult` into itself
+#![allow(clippy::useless_conversion)]
+
+use std::collections::HashMap;
+
+use numpy::ndarray::{Array3, ArrayD, IxDyn};
+use numpy::{IntoPyArray, PyArray1, PyArray3, PyArrayDyn};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict};
+
+use crate::config::Config;
+use crate::env::{self, EnvOptions, Observation, ObservationKind, Rewards, StepInfo};
+use crate::world::{MovementDirection, Snake, World};
+
+type Rgb = [u8; 3];
+
+const COLOR_EMPTY: Rgb = [255, 255, 255];
+const COLOR_FOOD: Rgb = [0, 255, 0];
+const COLOR_OBSTACLE: Rgb = [51, 51, 51];
+const COLOR_SNAKES: [Rgb; 4] = [[255, 0, 0], [26, 77, 230], [255, 140, 0], [140, 51, 191]];
+const COLOR_DEAD_SNAKE: Rgb = [102, 102, 102];
+
+/// Builds a configuration from keyword arguments, as if they had been given on the command line
+fn config_from_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Config> {
+    let mut args = Vec::new();
+    if let Some(kwargs) = kwargs {
+        for (key, value) in kwargs.iter() {
+            let flag = format!("--{}", key.extract::<String>()?.replace('_', "-"));
+            if value.is_none() {
+                continue;
+            }
+
+            if value.is_instance_of::<PyBool>() {
+                if value.extract::<bool>()? {
+                    args.push(flag);
+                }
+            } else {
+                args.push(flag);
+                args.push(value.str()?.to_string());
+            }
+        }
+    }
+
+    Config::from_args(args.into_iter()).map_err(PyValueError::new_err)
+}
+
+fn action(index: usize) -> PyResult<MovementDirection> {
+    env::ACTIONS.get(index)
+        .copied()
+        .ok_or_else(|| PyValueError::new_err(format!("invalid action: {} (expected 0 to {})", index, env::ACTIONS.len() - 1)))
+}
+
+fn action_index(direction: MovementDirection) -> usize {
+    env::ACTIONS.iter().position(|action| *action == direction).unwrap_or(0)
+}
+
+fn env_options(
+    config: Config,
+    observation: &str,
+    max_ticks: Option<u64>,
+    rewards: Option<HashMap<String, f32>>,
+) -> PyResult<EnvOptions> {
+    let mut options = EnvOptions::new(config);
+    options.observation = observation.parse::<ObservationKind>().map_err(PyValueError::new_err)?;
+    options.max_ticks = max_ticks;
+
+    for (name, reward) in rewards.unwrap_or_default() {
+        let rewards: &mut Rewards = &mut options.rewards;
+        match name.as_str() {
+            "food" => rewards.food = reward,
+            "death" => rewards.death = reward,
+            "step" => rewards.step = reward,
+            "win" => rewards.win = reward,
+            "approach" => rewards.approach = reward,
+            _ => return Err(PyValueError::new_err(format!("unknown reward: {} (expected one of food, death, step, win, approach)", name))),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Fails with Python's own `ModuleNotFoundError` if NumPy is missing, where creating an array
+/// would panic instead
+fn require_numpy(py: Python<'_>) -> PyResult<()> {
+    py.import_bound("numpy").map(|_| ())
+}
+
+/// Draws the board with `tile_size` pixels per tile, as a `rows x cols x 3` RGB image
+fn render(py: Python<'_>, world: &World, tile_size: usize) -> PyResult<Py<PyArray3<u8>>> {
+    if tile_size == 0 {
+        return Err(PyValueError::new_err("the tile size must be positive"));
+    }
+    require_numpy(py)?;
+
+    let mut image = Array3::zeros((world.row_count * tile_size, world.col_count * tile_size, 3));
+    for i_row in 0..world.row_count {
+        for i_col in 0..world.col_count {
+            let tile = [i_row, i_col];
+            let color = match world.snakes.iter().position(|snake| snake.body.contains(&tile)) {
+                Some(i) if world.snakes[i].is_alive() => COLOR_SNAKES[i % COLOR_SNAKES.len()],
+                Some(_) => COLOR_DEAD_SNAKE,
+                None if world.food == Some(tile) => COLOR_FOOD,
+                None if world.is_obstacle(tile) => COLOR_OBSTACLE,
+                None => COLOR_EMPTY,
+            };
+
+            for y in i_row * tile_size..(i_row + 1) * tile_size {
+                for x in i_col * tile_size..(i_col + 1) * tile_size {
+                    for (channel, value) in color.iter().enumerate() {
+                        image[[y, x, channel]] = *value;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(image.into_pyarray_bound(py).unbind())
+}
+
+fn snake_state<'py>(py: Python<'py>, snake: &Snake) -> PyResult<Bound<'py, PyDict>> {
+    let state = PyDict::new_bound(py);
+    state.set_item("body", snake.body.iter().map(|tile| (tile[0], tile[1])).collect::<Vec<_>>())?;
+    state.set_item("heading", action_index(snake.movement_direction))?;
+    state.set_item("alive", snake.is_alive())?;
+    state.set_item("death_cause", snake.death_cause.map(|cause| cause.name()))?;
+    state.set_item("food_eaten", snake.score.food_eaten)?;
+    state.set_item("ticks", snake.score.ticks)?;
+    Ok(state)
+}
+
+fn observation_array(py: Python<'_>, observation: Observation) -> PyResult<Py<PyArrayDyn<f32>>> {
+    require_numpy(py)?;
+    let array = ArrayD::from_shape_vec(IxDyn(&observation.shape), observation.data)
+        .map_err(|error| PyValueError::new_err(error.to_string()))?;
+    Ok(array.into_pyarray_bound(py).unbind())
+}
+
+fn info_dict<'py>(py: Python<'py>, info: StepInfo) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("tick", info.tick)?;
+    dict.set_item("food_eaten", info.food_eaten)?;
+    dict.set_item("length", info.length)?;
+    dict.set_item("death_cause", info.death_cause.map(|cause| cause.name()))?;
+    dict.set_item("truncated", info.truncated)?;
+    dict.set_item("seed", info.seed)?;
+    if let Some(observation) = info.final_observation {
+        dict.set_item("final_observation", observation_array(py, observation)?)?;
+    }
+    Ok(dict)
+}
+
+/// The game rules, stepped directly. Snakes are steered only by the actions passed to `step`.
+#[pyclass(name = "World", module = "rust_snake")]
+struct PyWorld {
+    config: Config,
+    world: World,
+}
+
+#[pymethods]
+impl PyWorld {
+    #[new]
+    #[pyo3(signature = (**kwargs))]
+    fn new(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<PyWorld> {
+        let config = config_from_kwargs(kwargs)?;
+        let world = World::from_config(&config);
+        Ok(PyWorld { config, world })
+    }
+
+    /// Advances one tick. `actions` holds one action or `None` per snake, in player order; a
+    /// single action steers snake 0. Snakes without an action keep going straight.
+    #[pyo3(signature = (actions = None))]
+    fn step(&mut self, actions: Option<&Bound<'_, PyAny>>) -> PyResult<()> {
+        let actions: Vec<Option<usize>> = match actions {
+            None => Vec::new(),
+            Some(actions) => match actions.extract::<usize>() {
+                Ok(action) => vec![Some(action)],
+                Err(_) => actions.extract()?,
+            },
+        };
+
+        for (snake, index) in self.world.snakes.iter_mut().zip(actions) {
+            if let Some(index) = index {
+                snake.queue_direction(action(index)?);
+            }
+        }
+
+        self.world.step();
+        Ok(())
+    }
+
+    /// Starts a new game, with `seed` if given or else one drawn from the previous game
+    #[pyo3(signature = (seed = None))]
+    fn reset(&mut self, seed: Option<u64>) {
+        match seed {
+            Some(seed) => self.world = World::from_config(&Config { seed: Some(seed), ..self.config.clone() }),
+            None => self.world.restart(),
+        }
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.world.is_game_over()
+    }
+
+    fn winner(&self) -> Option<usize> {
+        self.world.winner()
+    }
+
+    #[getter]
+    fn tick(&self) -> u64 {
+        self.world.tick
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.world.seed
+    }
+
+    #[getter]
+    fn shape(&self) -> (usize, usize) {
+        (self.world.row_count, self.world.col_count)
+    }
+
+    #[getter]
+    fn food(&self) -> Option<(usize, usize)> {
+        self.world.food.map(|food| (food[0], food[1]))
+    }
+
+    /// Everything about the game as plain Python values
+    fn state<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let state = PyDict::new_bound(py);
+        state.set_item("tick", self.world.tick)?;
+        state.set_item("seed", self.world.seed)?;
+        state.set_item("rows", self.world.row_count)?;
+        state.set_item("cols", self.world.col_count)?;
+        state.set_item("food", self.food())?;
+        state.set_item("is_game_over", self.world.is_game_over())?;
+        state.set_item("winner", self.world.winner())?;
+        state.set_item("map", self.world.map.as_ref().map(|map| map.name.clone()))?;
+        let snakes = self.world.snakes.iter()
+            .map(|snake| snake_state(py, snake))
+            .collect::<PyResult<Vec<_>>>()?;
+        state.set_item("snakes", snakes)?;
+        Ok(state)
+    }
+
+    #[pyo3(signature = (tile_size = 1))]
+    fn render(&self, py: Python<'_>, tile_size: usize) -> PyResult<Py<PyArray3<u8>>> {
+        render(py, &self.world, tile_size)
+    }
+}
+
+/// The reinforcement-learning environment: the agent steers snake 0 and bots steer the rest
+#[pyclass(name = "Env", module = "rust_snake")]
+struct PyEnv {
+    env: env::Env,
+}
+
+#[pymethods]
+impl PyEnv {
+    #[new]
+    #[pyo3(signature = (observation = "grid", max_ticks = None, rewards = None, **kwargs))]
+    fn new(
+        observation: &str,
+        max_ticks: Option<u64>,
+        rewards: Option<HashMap<String, f32>>,
+        kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<PyEnv> {
+        let options = env_options(config_from_kwargs(kwargs)?, observation, max_ticks, rewards)?;
+        Ok(PyEnv { env: env::Env::new(options) })
+    }
+
+    #[pyo3(signature = (seed = None))]
+    fn reset(&mut self, py: Python<'_>, seed: Option<u64>) -> PyResult<Py<PyArrayDyn<f32>>> {
+        observation_array(py, self.env.reset(seed))
+    }
+
+    /// Returns `(observation, reward, done, info)`
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(&mut self, py: Python<'py>, action_index: usize) -> PyResult<(Py<PyArrayDyn<f32>>, f32, bool, Bound<'py, PyDict>)> {
+        let result = self.env.step(action(action_index)?);
+        Ok((observation_array(py, result.observation)?, result.reward, result.done, info_dict(py, result.info)?))
+    }
+
+    #[getter]
+    fn observation_shape(&self) -> Vec<usize> {
+        self.env.observation_shape()
+    }
+
+    #[pyo3(signature = (tile_size = 1))]
+    fn render(&self, py: Python<'_>, tile_size: usize) -> PyResult<Py<PyArray3<u8>>> {
+        render(py, self.env.world(), tile_size)
+    }
+}
+
+/// Many environments stepped in parallel, outside the GIL
+#[pyclass(name = "VecEnv", module = "rust_snake")]
+struct PyVecEnv {
+    envs: env::VecEnv,
+}
+
+#[pymethods]
+impl PyVecEnv {
+    #[new]
+    #[pyo3(signature = (count, observation = "grid", max_ticks = None, rewards = None, **kwargs))]
+    fn new(
+        count: usize,
+        observation: &str,
+        max_ticks: Option<u64>,
+        rewards: Option<HashMap<String, f32>>,
+        kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<PyVecEnv> {
+        let options = env_options(config_from_kwargs(kwargs)?, observation, max_ticks, rewards)?;
+        Ok(PyVecEnv { envs: env::VecEnv::new(options, count) })
+    }
+
+    fn __len__(&self) -> usize {
+        self.envs.len()
+    }
+
+    /// Returns the observations stacked along a new first axis
+    #[pyo3(signature = (first_seed = None))]
+    fn reset(&mut self, py: Python<'_>, first_seed: Option<u64>) -> PyResult<Py<PyArrayDyn<f32>>> {
+        let observations = self.envs.reset(first_seed);
+        observation_array(py, stack(observations))
+    }
+
+    /// Returns `(observations, rewards, dones, infos)`, stacked along a new first axis
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        actions: Vec<usize>,
+    ) -> PyResult<(Py<PyArrayDyn<f32>>, Py<PyArray1<f32>>, Py<PyArray1<bool>>, Vec<Bound<'py, PyDict>>)> {
+        if actions.len() != self.envs.len() {
+            return Err(PyValueError::new_err(format!("expected {} actions, got {}", self.envs.len(), actions.len())));
+        }
+
+        let actions = actions.into_iter().map(action).collect::<PyResult<Vec<_>>>()?;
+        let envs = &mut self.envs;
+        require_numpy(py)?;
+        let results = py.allow_threads(|| envs.step(&actions));
+
+        let rewards: Vec<f32> = results.iter().map(|result| result.reward).collect();
+        let dones: Vec<bool> = results.iter().map(|result| result.done).collect();
+        let mut observations = Vec::with_capacity(results.len());
+        let mut infos = Vec::with_capacity(results.len());
+        for result in results {
+            observations.push(result.observation);
+            infos.push(info_dict(py, result.info)?);
+        }
+
+        Ok((
+            observation_array(py, stack(observations))?,
+            rewards.into_pyarray_bound(py).unbind(),
+            dones.into_pyarray_bound(py).unbind(),
+            infos,
+        ))
+    }
+
+    #[pyo3(signature = (index, tile_size = 1))]
+    fn render(&self, py: Python<'_>, index: usize, tile_size: usize) -> PyResult<Py<PyArray3<u8>>> {
+        let env = self.envs.envs()
+            .get(index)
+            .ok_or_else(|| PyValueError::new_err(format!("no environment {}", index)))?;
+        render(py, env.world(), tile_size)
+    }
+}
+
+/// Joins observations of the same shape into one with an extra leading axis
+fn stack(observations: Vec<Observation>) -> Observation {
+    let mut shape = vec![observations.len()];
+    shape.extend(observations.first().map(|observation| observation.shape.clone()).unwrap_or_default());
+    let data = observations.into_iter().flat_map(|observation| observation.data).collect();
+    Observation { shape, data }
+}
+
+#[pymodule]
+fn rust_snake(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyWorld>()?;
+    m.add_class::<PyEnv>()?;
+    m.add_class::<PyVecEnv>()?;
+    m.add("ACTIONS", env::ACTIONS.iter().map(|direction| format!("{:?}", direction).to_lowercase()).collect::<Vec<_>>())?;
+    m.add("OBSERVATIONS", ObservationKind::ALL.iter().map(|kind| kind.name()).collect::<Vec<_>>())?;
+    m.add("GRID_CHANNELS", env::GRID_CHANNELS.to_vec())?;
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::controller::Controller;
+use crate::world::{MovementDirection, World, WorldView};
+
+/// Bumped whenever a change to the format or to the rules would make older replays play back differently.
+/// Version 2 brought several snakes with head-on collisions, and maps in the recorded config.
+pub const REPLAY_FORMAT_VERSION: u32 = 2;
+
+/// A direction change applied at the start of a tick, counted from the start of the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayInput {
+    pub tick: u64,
+    /// Which player's snake turned
+    pub snake: usize,
+    pub direction: MovementDirection,
+}
+
+/// Everything needed to play a game back exactly: the configuration, including the seed,
+/// and the heading changes the world applied
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub config: Config,
+    pub inputs: Vec<ReplayInput>,
+}
+
+impl Replay {
+    /// Starts an empty recording of a game played in `world`, which was built from `config`
+    pub fn new(config: &Config, world: &World) -> Replay {
+        let mut config = config.clone();
+        config.seed = Some(world.seed);
+
+        Replay {
+            version: REPLAY_FORMAT_VERSION,
+            config,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let contents = fs::read_to_string(path)?;
+        let replay: Replay = serde_json::from_str(&contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        if replay.version != REPLAY_FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported replay version {} (expected {})", replay.version, REPLAY_FORMAT_VERSION),
+            ));
+        }
+
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(path, contents)
+    }
+
+    /// Creates the world the recorded game started from
+    pub fn world(&self) -> World {
+        World::from_config(&self.config)
+    }
+
+    /// Advances `world` by one tick under `controllers`, one per snake, recording the direction
+    /// changes the snakes applied
+    pub fn record_step(&mut self, world: &mut World, controllers: &mut [&mut dyn Controller]) {
+        let tick = world.tick;
+        let directions: Vec<MovementDirection> = world.snakes.iter().map(|snake| snake.movement_direction).collect();
+
+        world.step_with(controllers);
+
+        for (i, snake) in world.snakes.iter().enumerate() {
+            if snake.movement_direction != directions[i] {
+                self.inputs.push(ReplayInput { tick, snake: i, direction: snake.movement_direction });
+            }
+        }
+    }
+}
+
+/// Steers one snake exactly as it was steered in a recorded game. Games with several players
+/// need one replay controller per snake.
+#[derive(Debug, Clone)]
+pub struct ReplayController {
+    pub replay: Replay,
+    next_input: usize,
+}
+
+impl ReplayController {
+    pub fn new(replay: Replay) -> ReplayController {
+        ReplayController { replay, next_input: 0 }
+    }
+
+    /// One controller for each snake in the recorded game
+    pub fn for_players(replay: &Replay) -> Vec<ReplayController> {
+        (0..replay.config.players).map(|_| ReplayController::new(replay.clone())).collect()
+    }
+
+    /// Rewinds to the first input, to play the game again in a fresh copy of `replay.world()`
+    pub fn rewind(&mut self) {
+        self.next_input = 0;
+    }
+}
+
+impl Controller for ReplayController {
+    fn next_direction(&mut self, view: &WorldView) -> MovementDirection {
+        // Inputs are in tick order, so the other snakes' inputs and any this snake has already
+        // passed can be skipped for good
+        let inputs = &self.replay.inputs;
+        while inputs.get(self.next_input).is_some_and(|input| input.snake != view.you || input.tick < view.tick) {
+            self.next_input += 1;
+        }
+
+        match inputs.get(self.next_input) {
+            Some(input) if input.tick == view.tick => {
+                self.next_input += 1;
+                input.direction
+            }
+            _ => view.heading,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller;
+
+    /// Plays a seeded game between bots to the end, or for at most `max_ticks`, recording it
+    fn record_game(config: &Config, max_ticks: u64) -> (Replay, World) {
+        let mut world = World::from_config(config);
+        let mut recording = Replay::new(config, &world);
+        let mut bots: Vec<Box<dyn Controller>> = (0..config.players)
+            .map(|i| controller::controller_by_name(if i == 0 { "bfs" } else { "greedy" }, i as u64).unwrap())
+            .collect();
+
+        while !world.is_game_over() && world.tick < max_ticks {
+            let mut controllers: Vec<&mut dyn Controller> = bots.iter_mut()
+                .map(|bot| bot.as_mut() as &mut dyn Controller)
+                .collect();
+            recording.record_step(&mut world, &mut controllers);
+        }
+
+        (recording, world)
+    }
+
+    fn steering(controllers: &mut [ReplayController]) -> Vec<&mut dyn Controller> {
+        controllers.iter_mut().map(|controller| controller as &mut dyn Controller).collect()
+    }
+
+    fn play_back(replay: &Replay, ticks: u64) -> World {
+        let mut world = replay.world();
+        let mut controllers = ReplayController::for_players(replay);
+        for _ in 0..ticks {
+            world.step_with(&mut steering(&mut controllers));
+        }
+        world
+    }
+
+    #[test]
+    fn replay_reproduces_the_final_world() {
+        for players in 1..=3 {
+            let config = Config { seed: Some(11), players, ..Config::default() };
+            let (recording, world) = record_game(&config, 2000);
+            assert!(!recording.inputs.is_empty());
+
+            // Through the file format, as a shared replay would go
+            let replay: Replay = serde_json::from_str(&serde_json::to_string(&recording).unwrap()).unwrap();
+            let played_back = play_back(&replay, world.tick);
+
+            assert_eq!(played_back.tick, world.tick);
+            assert_eq!(played_back.snakes, world.snakes);
+            assert_eq!(played_back.food, world.food);
+        }
+    }
+
+    #[test]
+    fn rewound_controllers_play_the_game_again() {
+        let config = Config { seed: Some(3), ..Config::default() };
+        let (recording, world) = record_game(&config, 300);
+
+        let mut controllers = ReplayController::for_players(&recording);
+        for pass in 0..2 {
+            let mut played_back = recording.world();
+            while played_back.tick < world.tick {
+                played_back.step_with(&mut steering(&mut controllers));
+            }
+            assert_eq!(played_back.snakes, world.snakes, "pass {}", pass);
+            controllers.iter_mut().for_each(ReplayController::rewind);
+        }
+    }
+}
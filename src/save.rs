@@ -0,0 +1,149 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::replay::Replay;
+use crate::world::World;
+
+/// Bumped whenever `World` or `Config` change in a way older save files cannot be read into, or
+/// whenever `REPLAY_FORMAT_VERSION` changes, since a save carries the recording so far
+pub const SAVE_FORMAT_VERSION: u32 = 3;
+
+const SAVE_FILE_NAME: &str = "savegame.json";
+
+/// A game in progress, written when the window closes and picked up again on the next launch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub version: u32,
+    pub config: Config,
+    pub world: World,
+    /// The recording so far, so that a resumed game still produces a complete replay
+    pub replay: Replay,
+}
+
+impl SavedGame {
+    pub fn new(config: &Config, world: &World, replay: &Replay) -> SavedGame {
+        SavedGame {
+            version: SAVE_FORMAT_VERSION,
+            config: config.clone(),
+            world: world.clone(),
+            replay: replay.clone(),
+        }
+    }
+
+    /// The location of the save file in the user's data directory, if the platform has one
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rust-snake").join(SAVE_FILE_NAME))
+    }
+
+    /// Reads a saved game from `path`, returning `None` if there is none
+    pub fn load(path: &Path) -> io::Result<Option<SavedGame>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        // Check the version first so that an incompatible file is reported as such rather than
+        // as whichever field happened to fail to parse
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+
+        let header: Header = serde_json::from_str(&contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        if header.version != SAVE_FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported save version {} (expected {})", header.version, SAVE_FORMAT_VERSION),
+            ));
+        }
+
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let contents = serde_json::to_string(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(path, contents)
+    }
+
+    /// Deletes the save file at `path`, if there is one
+    pub fn remove(path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rust-snake-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn saved_game_round_trips_with_its_rng() {
+        let config = Config { seed: Some(3), ..Config::default() };
+        let mut world = World::from_config(&config);
+        for _ in 0..5 {
+            world.step();
+        }
+        let replay = Replay::new(&config, &world);
+
+        let path = temp_path("save-round-trip");
+        SavedGame::new(&config, &world, &replay).save(&path).unwrap();
+        let loaded = SavedGame::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let mut loaded = loaded.unwrap().expect("the game was saved");
+        assert_eq!(loaded.config, config);
+        assert_eq!(loaded.replay, replay);
+        assert_eq!(loaded.world.snakes, world.snakes);
+        assert_eq!((loaded.world.tick, loaded.world.food), (world.tick, world.food));
+
+        // The RNG carries on where it left off, so the next food lands in the same place
+        for _ in 0..3 {
+            world.spawn_food();
+            loaded.world.spawn_food();
+            assert_eq!(loaded.world.food, world.food);
+        }
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let config = Config { seed: Some(3), ..Config::default() };
+        let world = World::from_config(&config);
+        let replay = Replay::new(&config, &world);
+        let saved_game = SavedGame { version: SAVE_FORMAT_VERSION - 1, ..SavedGame::new(&config, &world, &replay) };
+
+        let path = temp_path("save-old-version");
+        saved_game.save(&path).unwrap();
+        let result = SavedGame::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("unsupported save version"));
+    }
+
+    #[test]
+    fn missing_file_is_no_saved_game() {
+        let path = temp_path("save-missing");
+        assert!(SavedGame::load(&path).unwrap().is_none());
+        assert!(SavedGame::remove(&path).is_ok());
+    }
+}
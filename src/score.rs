@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::world::Snake;
+
+/// How many scores are kept for each game mode and board size
+pub const HIGH_SCORE_TABLE_SIZE: usize = 10;
+
+const HIGH_SCORE_FILE_NAME: &str = "highscores.json";
+
+/// Progress of one snake in the current game
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+    pub food_eaten: u32,
+    pub ticks: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub food_eaten: u32,
+    pub length: usize,
+    pub ticks: u64,
+    /// Seconds since the Unix epoch
+    pub achieved_at: u64,
+}
+
+impl HighScore {
+    pub fn from_snake(snake: &Snake) -> HighScore {
+        HighScore {
+            food_eaten: snake.score.food_eaten,
+            length: snake.length(),
+            ticks: snake.score.ticks,
+            achieved_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        }
+    }
+}
+
+/// Best scores, grouped by game mode and board size so that only comparable games compete
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HighScoreTable {
+    pub tables: BTreeMap<String, Vec<HighScore>>,
+}
+
+impl HighScoreTable {
+    /// The location of the high-score file in the user's data directory, if the platform has one
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rust-snake").join(HIGH_SCORE_FILE_NAME))
+    }
+
+    /// The key under which games played with `config`'s mode, board size and map are ranked.
+    /// Games with several players are ranked separately from one-player games.
+    pub fn mode_key(config: &Config) -> String {
+        let mut key = format!("{} {} {}x{}", config.difficulty, config.boundary, config.row_count, config.col_count);
+        if let Some(map) = &config.map {
+            key = format!("{} {}", key, map.name);
+        }
+        if config.players > 1 { format!("{} {}p", key, config.players) } else { key }
+    }
+
+    /// Reads the table from the default path for a front-end to record scores in, along with
+    /// the path to save it back to. A file that exists but cannot be read is an error, so that
+    /// the front-end can leave it alone rather than save over it.
+    pub fn load_default() -> Result<(HighScoreTable, Option<PathBuf>), String> {
+        match HighScoreTable::default_path() {
+            Some(path) => match HighScoreTable::load(&path) {
+                Ok(table) => Ok((table, Some(path))),
+                Err(error) => Err(format!("Could not read high scores from {}: {}", path.display(), error)),
+            },
+            None => Ok((HighScoreTable::default(), None)),
+        }
+    }
+
+    /// Reads the table from `path`, treating a missing file as an empty table
+    pub fn load(path: &Path) -> io::Result<HighScoreTable> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(HighScoreTable::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(path, contents)
+    }
+
+    pub fn scores(&self, key: &str) -> &[HighScore] {
+        self.tables.get(key).map(|scores| scores.as_slice()).unwrap_or(&[])
+    }
+
+    /// Adds a score under `key`, returning its zero-based rank if it made it into the table.
+    /// More food ranks higher, and on a tie the faster game wins.
+    pub fn insert(&mut self, key: &str, score: HighScore) -> Option<usize> {
+        let scores = self.tables.entry(key.to_string()).or_default();
+        let rank = scores.iter()
+            .position(|other| (score.food_eaten, std::cmp::Reverse(score.ticks)) > (other.food_eaten, std::cmp::Reverse(other.ticks)))
+            .unwrap_or(scores.len());
+
+        if rank >= HIGH_SCORE_TABLE_SIZE {
+            return None;
+        }
+
+        scores.insert(rank, score);
+        scores.truncate(HIGH_SCORE_TABLE_SIZE);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn high_score(food_eaten: u32, ticks: u64) -> HighScore {
+        HighScore { food_eaten, length: food_eaten as usize + 3, ticks, achieved_at: 0 }
+    }
+
+    #[test]
+    fn insert_ranks_by_food_then_speed() {
+        let mut table = HighScoreTable::default();
+        assert_eq!(table.insert("mode", high_score(5, 100)), Some(0));
+        assert_eq!(table.insert("mode", high_score(7, 300)), Some(0));
+        assert_eq!(table.insert("mode", high_score(5, 80)), Some(1));
+        assert_eq!(table.insert("other", high_score(1, 10)), Some(0));
+
+        let ticks: Vec<u64> = table.scores("mode").iter().map(|score| score.ticks).collect();
+        assert_eq!(ticks, vec![300, 80, 100]);
+    }
+
+    #[test]
+    fn unreadable_table_is_an_error() {
+        let path = std::env::temp_dir().join(format!("rust-snake-highscores-{}.json", std::process::id()));
+        fs::write(&path, "not json").unwrap();
+
+        let result = HighScoreTable::load(&path);
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(contents, "not json");
+    }
+}
@@ -0,0 +1,292 @@
+//! The screens a front-end moves between and the menus on them, kept apart from drawing and
+//! input so that the flow of the game is easy to follow:
+//!
+//! ```text
+//! Title ──> Playing <──> Paused ──> Title
+//!   │          │           │
+//!   v          v           v
+//! Settings  GameOver    Settings ──> Paused
+//! ```
+//!
+//! Settings opened from the title screen return to it, and those opened from the pause menu
+//! return to the pause menu.
+
+use crate::config::{Config, MAX_PLAYERS, PRESETS};
+use crate::level::Difficulty;
+use crate::map::{Map, BUILT_IN_MAPS};
+use crate::world::BoundaryMode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    Title,
+    Playing,
+    Paused,
+    GameOver,
+    /// `from_pause` tells where the settings were opened from, and so where they go back to
+    Settings { from_pause: bool },
+}
+
+/// One line of a menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    NewGame,
+    Resume,
+    Restart,
+    Settings,
+    /// Leaves the game for the title screen
+    Title,
+    Quit,
+    Setting(Setting),
+    Back,
+}
+
+impl MenuItem {
+    pub fn label(self, settings: &Config) -> String {
+        match self {
+            MenuItem::NewGame => String::from("New game"),
+            MenuItem::Resume => String::from("Resume"),
+            MenuItem::Restart => String::from("Restart"),
+            MenuItem::Settings => String::from("Settings"),
+            MenuItem::Title => String::from("Quit to title"),
+            MenuItem::Quit => String::from("Quit"),
+            MenuItem::Setting(setting) => format!("{:<12}< {} >", setting.name(), setting.value(settings)),
+            MenuItem::Back => String::from("Back"),
+        }
+    }
+}
+
+/// A game setting that can be changed from the settings screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Difficulty,
+    Boundary,
+    Map,
+    Board,
+    Players,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 5] = [Setting::Difficulty, Setting::Boundary, Setting::Map, Setting::Board, Setting::Players];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Setting::Difficulty => "Difficulty",
+            Setting::Boundary => "Boundary",
+            Setting::Map => "Map",
+            Setting::Board => "Board",
+            Setting::Players => "Players",
+        }
+    }
+
+    pub fn value(self, config: &Config) -> String {
+        match self {
+            Setting::Difficulty => config.difficulty.to_string(),
+            Setting::Boundary => config.boundary.to_string(),
+            Setting::Map => config.map.as_ref().map(|map| map.name.clone()).unwrap_or_else(|| String::from("none")),
+            Setting::Board => PRESETS.iter()
+                .find(|(_, rows, cols)| *rows == config.row_count && *cols == config.col_count)
+                .map(|(name, _, _)| name.to_string())
+                .unwrap_or_else(|| format!("{}x{}", config.row_count, config.col_count)),
+            Setting::Players => config.players.to_string(),
+        }
+    }
+
+    /// Every configuration this setting can switch `config` to, in the order they cycle through
+    fn options(self, config: &Config) -> Vec<Config> {
+        match self {
+            Setting::Difficulty => Difficulty::ALL.iter()
+                .map(|difficulty| Config { difficulty: *difficulty, ..config.clone() })
+                .collect(),
+            Setting::Boundary => BoundaryMode::ALL.iter()
+                .map(|boundary| Config { boundary: *boundary, ..config.clone() })
+                .collect(),
+            Setting::Map => {
+                let mut options = vec![Config { map: None, ..config.clone() }];
+                for (name, _) in BUILT_IN_MAPS.iter() {
+                    if let Ok(map) = Map::find(name) {
+                        let mut option = Config { map: Some(map), ..config.clone() };
+                        option.apply_map();
+                        options.push(option);
+                    }
+                }
+                options
+            }
+            Setting::Board => PRESETS.iter()
+                .map(|(_, rows, cols)| Config { row_count: *rows, col_count: *cols, ..config.clone() })
+                .collect(),
+            Setting::Players => (1..=MAX_PLAYERS)
+                .map(|players| Config { players, ..config.clone() })
+                .collect(),
+        }
+    }
+
+    /// Moves the setting to its next value, or its previous one, skipping values that do not
+    /// go with the other settings, such as more players than the map has room for
+    pub fn change(self, config: &mut Config, forward: bool) {
+        let value = self.value(config);
+        let options: Vec<Config> = self.options(config).into_iter()
+            .filter(|option| option.validate().is_ok() || self.value(option) == value)
+            .collect();
+
+        let i_current = match options.iter().position(|option| self.value(option) == value) {
+            Some(i) => i,
+            None => return,
+        };
+        let i_next = if forward { (i_current + 1) % options.len() } else { (i_current + options.len() - 1) % options.len() };
+        *config = options[i_next].clone();
+    }
+}
+
+/// Which screen is showing and which menu item is selected on it
+#[derive(Debug, Clone)]
+pub struct Screens {
+    screen: Screen,
+    selected: usize,
+    /// Replays are played with the settings they were recorded with, so they have no settings
+    is_replay: bool,
+}
+
+impl Screens {
+    pub fn new(screen: Screen, is_replay: bool) -> Screens {
+        Screens { screen, selected: 0, is_replay }
+    }
+
+    pub fn screen(&self) -> Screen {
+        self.screen
+    }
+
+    /// Switches to `screen` with its first menu item selected
+    pub fn go_to(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.selected = 0;
+    }
+
+    /// Where Esc leads from the current screen, if anywhere
+    pub fn back(&self) -> Option<Screen> {
+        match self.screen {
+            Screen::Title => None,
+            Screen::Playing => Some(Screen::Paused),
+            Screen::Paused => Some(Screen::Playing),
+            Screen::GameOver => Some(Screen::Title),
+            Screen::Settings { from_pause: true } => Some(Screen::Paused),
+            Screen::Settings { from_pause: false } => Some(Screen::Title),
+        }
+    }
+
+    pub fn items(&self) -> Vec<MenuItem> {
+        match self.screen {
+            Screen::Title => vec![MenuItem::NewGame, MenuItem::Settings, MenuItem::Quit],
+            Screen::Playing | Screen::GameOver => Vec::new(),
+            Screen::Paused if self.is_replay => vec![MenuItem::Resume, MenuItem::Restart, MenuItem::Quit],
+            Screen::Paused => vec![MenuItem::Resume, MenuItem::Restart, MenuItem::Settings, MenuItem::Title, MenuItem::Quit],
+            Screen::Settings { .. } => Setting::ALL.iter()
+                .map(|setting| MenuItem::Setting(*setting))
+                .chain(Some(MenuItem::Back))
+                .collect(),
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_item(&self) -> Option<MenuItem> {
+        self.items().get(self.selected).copied()
+    }
+
+    /// Moves the selection down, or up, wrapping around the ends of the menu
+    pub fn move_selection(&mut self, down: bool) {
+        let count = self.items().len();
+        if count > 0 {
+            self.selected = if down { (self.selected + 1) % count } else { (self.selected + count - 1) % count };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_leads_where_the_diagram_says() {
+        let backs: Vec<Option<Screen>> = [
+            Screen::Title,
+            Screen::Playing,
+            Screen::Paused,
+            Screen::GameOver,
+            Screen::Settings { from_pause: true },
+            Screen::Settings { from_pause: false },
+        ]
+            .iter()
+            .map(|screen| Screens::new(*screen, false).back())
+            .collect();
+
+        assert_eq!(backs, vec![
+            None,
+            Some(Screen::Paused),
+            Some(Screen::Playing),
+            Some(Screen::Title),
+            Some(Screen::Paused),
+            Some(Screen::Title),
+        ]);
+    }
+
+    #[test]
+    fn selection_wraps_around_the_menu() {
+        let mut screens = Screens::new(Screen::Title, false);
+        screens.move_selection(false);
+        assert_eq!(screens.selected_item(), Some(MenuItem::Quit));
+        screens.move_selection(true);
+        assert_eq!(screens.selected_item(), Some(MenuItem::NewGame));
+
+        // Switching screens starts at the top again
+        screens.move_selection(true);
+        screens.go_to(Screen::Settings { from_pause: false });
+        assert_eq!(screens.selected(), 0);
+
+        let mut screens = Screens::new(Screen::Playing, false);
+        screens.move_selection(true);
+        assert_eq!(screens.selected_item(), None);
+    }
+
+    #[test]
+    fn replays_have_no_settings() {
+        let items = Screens::new(Screen::Paused, true).items();
+        assert!(!items.contains(&MenuItem::Settings));
+        assert!(Screens::new(Screen::Paused, false).items().contains(&MenuItem::Settings));
+    }
+
+    #[test]
+    fn settings_cycle_through_their_values() {
+        let mut config = Config::default();
+        Setting::Difficulty.change(&mut config, true);
+        assert_eq!(config.difficulty, Difficulty::Hard);
+        Setting::Difficulty.change(&mut config, true);
+        assert_eq!(config.difficulty, Difficulty::Easy);
+        Setting::Difficulty.change(&mut config, false);
+        assert_eq!(config.difficulty, Difficulty::Hard);
+
+        Setting::Map.change(&mut config, true);
+        let map = config.map.as_ref().unwrap();
+        assert_eq!(map.name, BUILT_IN_MAPS[0].0);
+        assert_eq!((config.row_count, config.col_count), (map.row_count, map.col_count));
+    }
+
+    #[test]
+    fn settings_skip_values_that_do_not_fit() {
+        let map = Map::parse("two", "..........\n..>....<..\n..........\n").unwrap();
+        let mut config = Config { map: Some(map), ..Config::default() };
+        config.apply_map();
+        config.players = 2;
+
+        // The map has room for two players, so the count wraps straight back to one
+        Setting::Players.change(&mut config, true);
+        assert_eq!(config.players, 1);
+        Setting::Players.change(&mut config, false);
+        assert_eq!(config.players, 2);
+
+        // The map fixes the board size, so no preset fits
+        Setting::Board.change(&mut config, true);
+        assert_eq!((config.row_count, config.col_count), (3, 10));
+    }
+}
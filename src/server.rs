@@ -0,0 +1,338 @@
+//! An authoritative game server. It alone steps the `World`, applying whatever inputs have
+//! arrived by each tick, and sends every client a snapshot after each tick. Slow or lagging
+//! clients never hold up the game, and a bot plays for any player whose client drops out.
+
+use std::collections::HashMap;
+use std::io::{self, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::controller::{self, Controller, KeyboardController, DEFAULT_BOT};
+use crate::net::{self, ClientMessage, PlayerStatus, ServerMessage, Snapshot, PROTOCOL_VERSION};
+use crate::timestep::FixedTimestep;
+use crate::world::World;
+
+/// How long the server waits for client messages before checking whether a tick is due
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// The most client messages handled between two checks for a due tick, so that a flood of
+/// messages from one client cannot hold up the game for everyone
+const MAX_EVENTS_PER_POLL: usize = 256;
+
+/// How many snapshots may queue up for a client before it starts missing some. Only the latest
+/// snapshot matters, so there is no point in a long queue.
+const OUTBOX_CAPACITY: usize = 4;
+
+/// A client that cannot take a snapshot for this long is disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// Stop after this many games, or never
+    pub rounds: Option<u32>,
+    /// The pause between the end of one game and the start of the next
+    pub round_break: Duration,
+}
+
+impl Default for ServerOptions {
+    fn default() -> ServerOptions {
+        ServerOptions {
+            rounds: None,
+            round_break: Duration::from_secs(3),
+        }
+    }
+}
+
+/// What the connection threads tell the game loop
+enum Event {
+    Connected { id: usize, stream: TcpStream, outbox: SyncSender<String>, writer: JoinHandle<()> },
+    Message { id: usize, message: ClientMessage },
+    Disconnected { id: usize },
+}
+
+struct Client {
+    stream: TcpStream,
+    outbox: SyncSender<String>,
+    writer: JoinHandle<()>,
+    /// The player slot, once the client has joined
+    player: Option<usize>,
+}
+
+/// One player slot in the game
+struct Player {
+    /// The connection playing in this slot, with the name it joined with
+    client: Option<(usize, String)>,
+    keyboard: KeyboardController,
+    /// Plays whenever no client does
+    bot: Box<dyn Controller>,
+    lag_ticks: u64,
+}
+
+/// Plays games on `listener` until `options.rounds` games have been played. The first game
+/// starts once every player slot has been taken; later ones start after a short break, with bots
+/// standing in for players who have left.
+pub fn serve(listener: TcpListener, config: &Config, options: &ServerOptions) -> io::Result<()> {
+    let (events, event_receiver) = mpsc::channel();
+    thread::spawn(move || accept_connections(listener, events));
+
+    let mut world = World::from_config(config);
+    world.is_running = false;
+
+    let mut players: Vec<Player> = (0..config.players)
+        .map(|i_player| Player {
+            client: None,
+            keyboard: KeyboardController::new(),
+            bot: bot(config, world.seed.wrapping_add(i_player as u64)),
+            lag_ticks: 0,
+        })
+        .collect();
+
+    let mut server = Server { config: config.clone(), clients: HashMap::new(), round: 1 };
+    let mut round_over_at: Option<Instant> = None;
+    let mut timestep = FixedTimestep::new(config.tick_rate(1));
+
+    loop {
+        // Wait briefly for a message, then take whatever else has arrived, and check for a due
+        // tick whether or not there were any
+        match event_receiver.recv_timeout(EVENT_POLL_INTERVAL) {
+            Ok(event) => server.handle(event, &world, &mut players),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        for event in event_receiver.try_iter().take(MAX_EVENTS_PER_POLL) {
+            server.handle(event, &world, &mut players);
+        }
+
+        // Wait in the lobby until the first game has a full set of players
+        if server.round == 1 && world.tick == 0 && !world.is_running {
+            if players.iter().all(|player| player.client.is_some()) {
+                world.is_running = true;
+                timestep.reset();
+            }
+            continue;
+        }
+
+        if let Some(over_at) = round_over_at {
+            if over_at.elapsed() >= options.round_break {
+                round_over_at = None;
+                server.round += 1;
+                world.restart();
+                players.iter_mut().for_each(|player| player.keyboard.clear());
+                timestep.reset();
+                server.broadcast(&world, &players);
+            }
+            continue;
+        }
+
+        let level = config.speed_curve().level(world.max_food_eaten());
+        timestep.set_tick_rate(config.scaled_tick_rate(level, 1.0));
+
+        for _ in 0..timestep.update(Instant::now()) {
+            let mut controllers: Vec<&mut dyn Controller> = players.iter_mut()
+                .map(|player| match player.client {
+                    Some(_) => &mut player.keyboard as &mut dyn Controller,
+                    None => player.bot.as_mut() as &mut dyn Controller,
+                })
+                .collect();
+            world.step_with(&mut controllers);
+            server.broadcast(&world, &players);
+
+            if world.is_game_over() {
+                if options.rounds.is_some_and(|rounds| server.round >= rounds) {
+                    server.close_all();
+                    return Ok(());
+                }
+
+                round_over_at = Some(Instant::now());
+                break;
+            }
+        }
+    }
+}
+
+fn bot(config: &Config, seed: u64) -> Box<dyn Controller> {
+    let name = config.ai.as_deref().unwrap_or(DEFAULT_BOT);
+    controller::controller_by_name(name, seed).unwrap()
+}
+
+struct Server {
+    config: Config,
+    clients: HashMap<usize, Client>,
+    round: u32,
+}
+
+impl Server {
+    fn handle(&mut self, event: Event, world: &World, players: &mut [Player]) {
+        match event {
+            Event::Connected { id, stream, outbox, writer } => {
+                self.clients.insert(id, Client { stream, outbox, writer, player: None });
+            }
+            Event::Message { id, message } => self.handle_message(id, message, world, players),
+            Event::Disconnected { id } => self.disconnect(id, players),
+        }
+    }
+
+    fn handle_message(&mut self, id: usize, message: ClientMessage, world: &World, players: &mut [Player]) {
+        let player = match self.clients.get(&id) {
+            Some(client) => client.player,
+            None => return,
+        };
+
+        match (message, player) {
+            (ClientMessage::Join { version, name }, None) => {
+                if version != PROTOCOL_VERSION {
+                    let reason = format!("unsupported protocol version {} (expected {})", version, PROTOCOL_VERSION);
+                    self.reject(id, reason);
+                    return;
+                }
+
+                let i_player = match players.iter().position(|player| player.client.is_none()) {
+                    Some(i_player) => i_player,
+                    None => {
+                        self.reject(id, String::from("the game is full"));
+                        return;
+                    }
+                };
+
+                players[i_player].client = Some((id, name));
+                players[i_player].keyboard.clear();
+                players[i_player].lag_ticks = 0;
+                if let Some(client) = self.clients.get_mut(&id) {
+                    client.player = Some(i_player);
+                }
+
+                self.send(id, &ServerMessage::Welcome { player: i_player, config: Box::new(self.config.clone()) });
+                self.broadcast(world, players);
+            }
+            (ClientMessage::Input { direction, tick }, Some(i_player)) => {
+                // Inputs are applied on the next tick the server runs, however late they are,
+                // rather than rewinding the game for a single player
+                let player = &mut players[i_player];
+                player.keyboard.press(direction);
+                player.lag_ticks = world.tick.saturating_sub(tick);
+            }
+            (ClientMessage::Leave, _) => self.disconnect(id, players),
+            // Anything else is out of order, such as an input before joining
+            _ => self.disconnect(id, players),
+        }
+    }
+
+    fn send(&mut self, id: usize, message: &ServerMessage) {
+        let line = serde_json::to_string(message).expect("server messages always serialize");
+        let is_closed = match self.clients.get(&id) {
+            // A full outbox means the client is behind, and it is better off skipping a snapshot
+            Some(client) => matches!(client.outbox.try_send(line), Err(TrySendError::Disconnected(_))),
+            None => false,
+        };
+
+        if is_closed {
+            self.clients.remove(&id);
+        }
+    }
+
+    fn broadcast(&mut self, world: &World, players: &[Player]) {
+        let statuses = players.iter()
+            .map(|player| PlayerStatus {
+                name: player.client.as_ref().map(|(_, name)| name.clone()),
+                lag_ticks: player.lag_ticks,
+            })
+            .collect();
+        let message = ServerMessage::Snapshot(Snapshot::new(self.round, world, statuses));
+
+        let ids: Vec<usize> = self.clients.iter()
+            .filter(|(_, client)| client.player.is_some())
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            self.send(id, &message);
+        }
+    }
+
+    fn reject(&mut self, id: usize, reason: String) {
+        self.send(id, &ServerMessage::Rejected { reason });
+        // Dropping the outbox lets the writer thread send the rejection before it closes
+        self.clients.remove(&id);
+    }
+
+    /// Forgets a client and hands its snake to a bot until someone else joins
+    fn disconnect(&mut self, id: usize, players: &mut [Player]) {
+        if let Some(client) = self.clients.remove(&id) {
+            let _ = client.stream.shutdown(Shutdown::Both);
+            if let Some(i_player) = client.player {
+                players[i_player].client = None;
+                players[i_player].keyboard.clear();
+            }
+        }
+    }
+
+    /// Closes every connection once the snapshots already queued for it have been sent
+    fn close_all(&mut self) {
+        for (_, client) in self.clients.drain() {
+            drop(client.outbox);
+            let _ = client.writer.join();
+        }
+    }
+}
+
+fn accept_connections(listener: TcpListener, events: Sender<Event>) {
+    for (id, stream) in listener.incoming().enumerate() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+
+        if spawn_connection(id, stream, &events).is_err() {
+            continue;
+        }
+    }
+}
+
+/// Starts a reader and a writer thread for a new connection, so that neither a slow client nor
+/// a silent one can block the game loop
+fn spawn_connection(id: usize, stream: TcpStream, events: &Sender<Event>) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+    let reader = stream.try_clone()?;
+    let writer_stream = stream.try_clone()?;
+    let (outbox, outbox_receiver) = mpsc::sync_channel(OUTBOX_CAPACITY);
+
+    let writer_events = events.clone();
+    let writer = thread::spawn(move || write_messages(id, writer_stream, outbox_receiver, writer_events));
+
+    if events.send(Event::Connected { id, stream, outbox, writer }).is_err() {
+        return Ok(());
+    }
+
+    let reader_events = events.clone();
+    thread::spawn(move || read_messages(id, reader, reader_events));
+
+    Ok(())
+}
+
+fn read_messages(id: usize, stream: TcpStream, events: Sender<Event>) {
+    let mut reader = BufReader::new(stream);
+    // A read error, a malformed message and a closed connection all end the client's game
+    while let Ok(Some(message)) = net::read_message(&mut reader) {
+        if events.send(Event::Message { id, message }).is_err() {
+            return;
+        }
+    }
+
+    let _ = events.send(Event::Disconnected { id });
+}
+
+fn write_messages(id: usize, mut stream: TcpStream, outbox: Receiver<String>, events: Sender<Event>) {
+    for mut line in outbox {
+        line.push('\n');
+        if stream.write_all(line.as_bytes()).and_then(|_| stream.flush()).is_err() {
+            let _ = events.send(Event::Disconnected { id });
+            return;
+        }
+    }
+
+    let _ = stream.shutdown(Shutdown::Both);
+}
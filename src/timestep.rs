@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+
+/// Ticks beyond this many per update are dropped rather than simulated, so that a long stall
+/// (a suspended laptop, a debugger breakpoint) does not fast-forward the game
+pub const MAX_TICKS_PER_UPDATE: u32 = 5;
+
+/// Turns elapsed monotonic time into a whole number of simulation ticks at a fixed rate,
+/// carrying the remainder over so that ticks neither drift nor depend on the frame rate
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    tick_duration: Duration,
+    accumulator: Duration,
+    last_update: Option<Instant>,
+}
+
+impl FixedTimestep {
+    pub fn new(ticks_per_second: f64) -> FixedTimestep {
+        FixedTimestep {
+            tick_duration: tick_duration(ticks_per_second),
+            accumulator: Duration::from_secs(0),
+            last_update: None,
+        }
+    }
+
+    pub fn tick_rate(&self) -> f64 {
+        1.0 / self.tick_duration.as_secs_f64()
+    }
+
+    /// Changes the tick rate, keeping any time already accumulated towards the next tick
+    pub fn set_tick_rate(&mut self, ticks_per_second: f64) {
+        self.tick_duration = tick_duration(ticks_per_second);
+    }
+
+    /// Forgets accumulated time, e.g. after the game was paused or restarted
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::from_secs(0);
+        self.last_update = None;
+    }
+
+    /// Returns how many ticks are due at `now`. The first call only starts the clock.
+    pub fn update(&mut self, now: Instant) -> u32 {
+        let elapsed = match self.last_update {
+            Some(last_update) => now.saturating_duration_since(last_update),
+            None => Duration::from_secs(0),
+        };
+        self.last_update = Some(now);
+        self.accumulator += elapsed;
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick_duration {
+            self.accumulator -= self.tick_duration;
+            ticks += 1;
+        }
+
+        if ticks > MAX_TICKS_PER_UPDATE {
+            ticks = MAX_TICKS_PER_UPDATE;
+        }
+
+        ticks
+    }
+}
+
+fn tick_duration(ticks_per_second: f64) -> Duration {
+    Duration::from_secs_f64(1.0 / ticks_per_second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn the_first_update_only_starts_the_clock() {
+        let mut timestep = FixedTimestep::new(10.0);
+        assert_eq!(timestep.update(Instant::now() + ms(1000)), 0);
+    }
+
+    #[test]
+    fn time_accumulates_across_updates() {
+        let start = Instant::now();
+        let mut timestep = FixedTimestep::new(10.0);
+        timestep.update(start);
+
+        assert_eq!(timestep.update(start + ms(60)), 0);
+        assert_eq!(timestep.update(start + ms(120)), 1);
+        assert_eq!(timestep.update(start + ms(190)), 0);
+        assert_eq!(timestep.update(start + ms(410)), 3);
+    }
+
+    #[test]
+    fn a_long_stall_is_capped() {
+        let start = Instant::now();
+        let mut timestep = FixedTimestep::new(10.0);
+        timestep.update(start);
+
+        assert_eq!(timestep.update(start + ms(60_000)), MAX_TICKS_PER_UPDATE);
+        // The dropped ticks are gone rather than played later
+        assert_eq!(timestep.update(start + ms(60_050)), 0);
+    }
+
+    #[test]
+    fn reset_forgets_accumulated_time() {
+        let start = Instant::now();
+        let mut timestep = FixedTimestep::new(10.0);
+        timestep.update(start);
+        timestep.update(start + ms(90));
+
+        timestep.reset();
+        assert_eq!(timestep.update(start + ms(500)), 0);
+        assert_eq!(timestep.update(start + ms(590)), 0);
+        assert_eq!(timestep.update(start + ms(600)), 1);
+    }
+
+    #[test]
+    fn changing_the_rate_keeps_accumulated_time() {
+        let start = Instant::now();
+        let mut timestep = FixedTimestep::new(10.0);
+        timestep.update(start);
+        timestep.update(start + ms(60));
+
+        timestep.set_tick_rate(20.0);
+        assert!((timestep.tick_rate() - 20.0).abs() < 1e-9);
+        assert_eq!(timestep.update(start + ms(110)), 2);
+    }
+}
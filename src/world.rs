@@ -0,0 +1,819 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{thread_rng, Rng, RngCore, SeedableRng};
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::controller::Controller;
+use crate::map::{Map, START_LENGTH};
+use crate::score::Score;
+
+/// How many direction changes can be buffered ahead of the ticks that apply them
+pub const INPUT_QUEUE_CAPACITY: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementDirection {
+    Up,
+    Left,
+    Down,
+    Right,
+}
+
+impl MovementDirection {
+    pub fn opposite(self) -> MovementDirection {
+        match self {
+            MovementDirection::Up => MovementDirection::Down,
+            MovementDirection::Left => MovementDirection::Right,
+            MovementDirection::Down => MovementDirection::Up,
+            MovementDirection::Right => MovementDirection::Left,
+        }
+    }
+
+    /// The heading after a quarter turn counter-clockwise
+    pub fn turn_left(self) -> MovementDirection {
+        match self {
+            MovementDirection::Up => MovementDirection::Left,
+            MovementDirection::Left => MovementDirection::Down,
+            MovementDirection::Down => MovementDirection::Right,
+            MovementDirection::Right => MovementDirection::Up,
+        }
+    }
+
+    /// The heading after a quarter turn clockwise
+    pub fn turn_right(self) -> MovementDirection {
+        self.turn_left().opposite()
+    }
+}
+
+/// What happens when the snake's head leaves the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BoundaryMode {
+    /// The head reappears on the opposite edge, in both axes
+    #[default]
+    Wrap,
+    /// Every edge is a solid wall
+    Walls,
+    /// The left and right edges wrap, the top and bottom edges are walls
+    WrapHorizontal,
+    /// The top and bottom edges wrap, the left and right edges are walls
+    WrapVertical,
+}
+
+impl BoundaryMode {
+    pub const ALL: [BoundaryMode; 4] = [
+        BoundaryMode::Wrap,
+        BoundaryMode::Walls,
+        BoundaryMode::WrapHorizontal,
+        BoundaryMode::WrapVertical,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BoundaryMode::Wrap => "wrap",
+            BoundaryMode::Walls => "walls",
+            BoundaryMode::WrapHorizontal => "wrap-horizontal",
+            BoundaryMode::WrapVertical => "wrap-vertical",
+        }
+    }
+
+    pub fn wraps_rows(self) -> bool {
+        self == BoundaryMode::Wrap || self == BoundaryMode::WrapVertical
+    }
+
+    pub fn wraps_cols(self) -> bool {
+        self == BoundaryMode::Wrap || self == BoundaryMode::WrapHorizontal
+    }
+}
+
+impl fmt::Display for BoundaryMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for BoundaryMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BoundaryMode, String> {
+        BoundaryMode::ALL.iter()
+            .copied()
+            .find(|mode| mode.name() == s)
+            .ok_or_else(|| format!("unknown boundary mode: {} (expected one of wrap, walls, wrap-horizontal, wrap-vertical)", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeathCause {
+    SelfCollision,
+    Wall,
+    /// Ran into the body of another snake
+    SnakeCollision,
+    /// Met another snake head to head and was not the longer one
+    HeadOn,
+    /// Ran into a wall drawn on the map
+    Obstacle,
+}
+
+impl DeathCause {
+    pub fn name(self) -> &'static str {
+        match self {
+            DeathCause::SelfCollision => "self-collision",
+            DeathCause::Wall => "wall",
+            DeathCause::SnakeCollision => "snake-collision",
+            DeathCause::HeadOn => "head-on",
+            DeathCause::Obstacle => "obstacle",
+        }
+    }
+}
+
+impl fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Returns the tile one step away from `tile` in `direction` on a board of the given size,
+/// wrapping around the edges the boundary mode allows, or `None` if the step would hit a wall
+fn step_tile(
+    boundary: BoundaryMode,
+    row_count: usize,
+    col_count: usize,
+    tile: [usize; 2],
+    direction: MovementDirection,
+) -> Option<[usize; 2]> {
+    let offset = |n: usize, delta: i32, max: usize, wraps: bool| {
+        let n = n as i32 + delta;
+        let max = max as i32;
+        if n >= 0 && n < max {
+            Some(n as usize)
+        } else if wraps {
+            Some(((max + (n % max)) % max) as usize)
+        } else {
+            None
+        }
+    };
+
+    let wraps_rows = boundary.wraps_rows();
+    let wraps_cols = boundary.wraps_cols();
+
+    match direction {
+        MovementDirection::Up => offset(tile[0], -1, row_count, wraps_rows).map(|row| [row, tile[1]]),
+        MovementDirection::Left => offset(tile[1], -1, col_count, wraps_cols).map(|col| [tile[0], col]),
+        MovementDirection::Down => offset(tile[0], 1, row_count, wraps_rows).map(|row| [row, tile[1]]),
+        MovementDirection::Right => offset(tile[1], 1, col_count, wraps_cols).map(|col| [tile[0], col]),
+    }
+}
+
+/// One snake on the board, with its own heading, buffered turns and score
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snake {
+    /// From the tail to the head
+    pub body: Vec<[usize; 2]>,
+    pub movement_direction: MovementDirection,
+    pub input_queue: VecDeque<MovementDirection>,
+    pub death_cause: Option<DeathCause>,
+    pub score: Score,
+}
+
+impl Snake {
+    pub fn new(body: Vec<[usize; 2]>, movement_direction: MovementDirection) -> Snake {
+        Snake {
+            body,
+            movement_direction,
+            input_queue: VecDeque::new(),
+            death_cause: None,
+            score: Score::default(),
+        }
+    }
+
+    pub fn head(&self) -> Option<[usize; 2]> {
+        self.body.last().copied()
+    }
+
+    pub fn length(&self) -> usize {
+        self.body.len()
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.death_cause.is_none()
+    }
+
+    /// The tiles that can kill a snake moving onto them next tick, assuming a live snake's tail
+    /// moves out of the way, which it does unless the snake eats. A dead snake stays where it is.
+    pub fn blocking_body(&self) -> &[[usize; 2]] {
+        if self.is_alive() && !self.body.is_empty() { &self.body[1..] } else { &self.body }
+    }
+
+    /// Buffers a direction change to be applied on a later tick, one change per tick.
+    /// Returns false if the change was rejected because it would reverse or repeat the heading
+    /// the snake will have by then, or because the queue is full.
+    pub fn queue_direction(&mut self, direction: MovementDirection) -> bool {
+        let heading = *self.input_queue.back().unwrap_or(&self.movement_direction);
+        if direction == heading || direction == heading.opposite() {
+            return false;
+        }
+
+        if self.input_queue.len() >= INPUT_QUEUE_CAPACITY {
+            return false;
+        }
+
+        self.input_queue.push_back(direction);
+        true
+    }
+}
+
+/// A read-only snapshot of what a controller may look at when choosing the next heading
+#[derive(Debug, Clone, Copy)]
+pub struct WorldView<'a> {
+    /// The snake being steered, from the tail to the head
+    pub snake_body: &'a [[usize; 2]],
+    pub heading: MovementDirection,
+    /// Every snake on the board, including the one being steered
+    pub snakes: &'a [Snake],
+    /// The index of the snake being steered in `snakes`
+    pub you: usize,
+    pub food: Option<[usize; 2]>,
+    pub row_count: usize,
+    pub col_count: usize,
+    pub boundary: BoundaryMode,
+    /// Ticks played so far in this game
+    pub tick: u64,
+    /// The walls and start positions of the board, if it has a map
+    pub map: Option<&'a Map>,
+}
+
+impl<'a> WorldView<'a> {
+    pub fn head(&self) -> Option<[usize; 2]> {
+        self.snake_body.last().copied()
+    }
+
+    /// The tile one step from `tile` in `direction`, as `step_tile` finds it
+    pub fn next_tile(&self, tile: [usize; 2], direction: MovementDirection) -> Option<[usize; 2]> {
+        step_tile(self.boundary, self.row_count, self.col_count, tile, direction)
+    }
+
+    pub fn is_obstacle(&self, tile: [usize; 2]) -> bool {
+        self.map.is_some_and(|map| map.is_wall(tile))
+    }
+
+    /// Whether moving the head onto `tile` next tick would run into a snake or a wall of the
+    /// map, assuming every live snake's tail moves out of the way
+    pub fn is_blocked(&self, tile: [usize; 2]) -> bool {
+        self.is_obstacle(tile) || self.snakes.iter().any(|snake| snake.blocking_body().contains(&tile))
+    }
+
+    /// The live snakes other than the one being steered
+    pub fn opponents(&self) -> impl Iterator<Item=&'a Snake> {
+        let you = self.you;
+        self.snakes.iter()
+            .enumerate()
+            .filter(move |(i, snake)| *i != you && snake.is_alive())
+            .map(|(_, snake)| snake)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct World {
+    pub is_running: bool,
+    pub row_count: usize,
+    pub col_count: usize,
+    pub boundary: BoundaryMode,
+    pub seed: u64,
+    pub rng: ChaCha8Rng,
+    /// One snake per player, in player order
+    pub snakes: Vec<Snake>,
+    pub food: Option<[usize; 2]>,
+    /// Ticks played so far in this game
+    pub tick: u64,
+    /// Walls, start positions and food zones, when the board is not left open
+    #[serde(default)]
+    pub map: Option<Map>,
+}
+
+impl World {
+    /// Creates a one-player world with a random seed. Use `World::with_seed` to reproduce an earlier run.
+    pub fn new(rows: usize, cols: usize) -> World {
+        World::with_seed(rows, cols, thread_rng().gen())
+    }
+
+    /// Creates a world with the board size, boundary mode, map, player count and seed from `config`
+    pub fn from_config(config: &Config) -> World {
+        let seed = config.seed.unwrap_or_else(|| thread_rng().gen());
+        let mut world = match &config.map {
+            Some(map) => World::with_map(map.clone(), config.players, seed),
+            None => World::with_players(config.row_count, config.col_count, config.players, seed),
+        };
+
+        world.boundary = config.boundary;
+        world
+    }
+
+    /// Creates a one-player world seeded from another random number generator
+    pub fn from_rng<R: RngCore>(rows: usize, cols: usize, rng: &mut R) -> World {
+        World::with_seed(rows, cols, rng.next_u64())
+    }
+
+    /// Creates a one-player world whose food placement, and therefore whole game given the same
+    /// inputs, is fully determined by `seed`
+    pub fn with_seed(rows: usize, cols: usize, seed: u64) -> World {
+        World::with_players(rows, cols, 1, seed)
+    }
+
+    /// Creates a world with `players` snakes, seeded like `World::with_seed`. Panics unless the
+    /// board has at least one row per player and room for a snake across it.
+    pub fn with_players(rows: usize, cols: usize, players: usize, seed: u64) -> World {
+        World::build(rows, cols, players, seed, None)
+    }
+
+    /// Creates a world on the board of `map`, with the snakes at its start positions
+    pub fn with_map(map: Map, players: usize, seed: u64) -> World {
+        World::build(map.row_count, map.col_count, players, seed, Some(map))
+    }
+
+    fn build(rows: usize, cols: usize, players: usize, seed: u64, map: Option<Map>) -> World {
+        // Snakes without a start on the map are laid out across the board, one row each
+        let players = players.max(1);
+        if players > map.as_ref().map_or(0, |map| map.starts.len()) {
+            assert!(
+                cols >= START_LENGTH && rows >= players,
+                "a board of {} rows by {} columns is too small for {} players: it needs one row per player and {} columns",
+                rows, cols, players, START_LENGTH,
+            );
+        }
+
+        let mut world = World {
+            is_running: true,
+            row_count: rows,
+            col_count: cols,
+            boundary: BoundaryMode::default(),
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            snakes: vec![Snake::new(Vec::new(), MovementDirection::Right); players],
+            food: None,
+            tick: 0,
+            map,
+        };
+
+        world.init();
+        world
+    }
+
+    /// Puts every snake back at its starting position. On a map, the snakes start where the map
+    /// says. Otherwise player 1 starts in the top-left corner heading right; further players are
+    /// spread down the board, alternately starting on the right heading left and on the left
+    /// heading right.
+    pub fn init(&mut self) {
+        self.is_running = true;
+        self.tick = 0;
+
+        let player_count = self.snakes.len();
+        let last_col = self.col_count - 1;
+        let starts = self.map.as_ref().map(|map| map.starts.as_slice()).unwrap_or(&[]);
+        for (i, snake) in self.snakes.iter_mut().enumerate() {
+            let row = i * self.row_count / player_count;
+            *snake = if let Some(start) = starts.get(i) {
+                Snake::new(start.body(), start.heading)
+            } else if i.is_multiple_of(2) {
+                Snake::new((0..START_LENGTH).map(|i_col| [row, i_col]).collect(), MovementDirection::Right)
+            } else {
+                Snake::new((0..START_LENGTH).map(|i_col| [row, last_col - i_col]).collect(), MovementDirection::Left)
+            };
+        }
+
+        self.spawn_food();
+    }
+
+    /// Starts a new game on the same board. The new game's seed is drawn from the current RNG,
+    /// so a seeded session stays reproducible and `seed` always identifies the game in progress.
+    pub fn restart(&mut self) {
+        self.seed = self.rng.gen();
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+        self.init();
+    }
+
+    /// Places the food on a random tile not covered by a snake or a wall, or removes it if the
+    /// board is full. Food only appears in the map's food zones while any of them is free.
+    pub fn spawn_food(&mut self) {
+        let empty_tiles: Vec<[usize; 2]> = (0..self.row_count)
+            .flat_map(|i_row| (0..self.col_count).map(move |i_col| [i_row, i_col]))
+            .filter(|tile| !self.snakes.iter().any(|snake| snake.body.contains(tile)))
+            .filter(|tile| !self.is_obstacle(*tile))
+            .collect();
+
+        let zone_tiles: Vec<[usize; 2]> = match &self.map {
+            Some(map) => empty_tiles.iter().copied().filter(|tile| map.is_food_zone(*tile)).collect(),
+            None => Vec::new(),
+        };
+
+        let tiles = if zone_tiles.is_empty() { &empty_tiles } else { &zone_tiles };
+        self.food = tiles.choose(&mut self.rng).copied();
+    }
+
+    pub fn is_obstacle(&self, tile: [usize; 2]) -> bool {
+        self.map.as_ref().is_some_and(|map| map.is_wall(tile))
+    }
+
+    /// A one-player game is over when its snake dies. A game with several players is over once
+    /// at most one snake is left alive.
+    pub fn is_game_over(&self) -> bool {
+        let alive = self.snakes.iter().filter(|snake| snake.is_alive()).count();
+        if self.snakes.len() > 1 { alive <= 1 } else { alive == 0 }
+    }
+
+    /// The last snake standing in a finished game with several players, or `None` while the game
+    /// goes on, in a one-player game, or when the last snakes died on the same tick
+    pub fn winner(&self) -> Option<usize> {
+        if self.snakes.len() < 2 || !self.is_game_over() {
+            return None;
+        }
+
+        self.snakes.iter().position(|snake| snake.is_alive())
+    }
+
+    /// The most food any snake has eaten, which sets the level of the game
+    pub fn max_food_eaten(&self) -> u32 {
+        self.snakes.iter().map(|snake| snake.score.food_eaten).max().unwrap_or(0)
+    }
+
+    /// What the controller of snake `you` gets to see
+    pub fn view(&self, you: usize) -> WorldView<'_> {
+        let snake = &self.snakes[you];
+        WorldView {
+            snake_body: &snake.body,
+            heading: snake.movement_direction,
+            snakes: &self.snakes,
+            you,
+            food: self.food,
+            row_count: self.row_count,
+            col_count: self.col_count,
+            boundary: self.boundary,
+            tick: self.tick,
+            map: self.map.as_ref(),
+        }
+    }
+
+    /// The tile one step from `tile` in `direction`, as `step_tile` finds it
+    pub fn next_tile(&self, tile: [usize; 2], direction: MovementDirection) -> Option<[usize; 2]> {
+        step_tile(self.boundary, self.row_count, self.col_count, tile, direction)
+    }
+
+    /// Asks each live snake's controller for a heading, in player order, and advances one tick.
+    /// Snakes without a controller keep going. Controllers are only consulted when the tick will
+    /// actually happen, so none is ever asked twice about the same tick.
+    pub fn step_with(&mut self, controllers: &mut [&mut dyn Controller]) {
+        if self.is_running && !self.is_game_over() {
+            for (i, controller) in controllers.iter_mut().enumerate().take(self.snakes.len()) {
+                if self.snakes[i].is_alive() {
+                    let direction = controller.next_direction(&self.view(i));
+                    self.snakes[i].queue_direction(direction);
+                }
+            }
+        }
+
+        self.step();
+    }
+
+    /// Moves every live snake one tile at the same time. A snake dies when it hits a wall, an
+    /// obstacle on the map, a body, or another snake's head on the same tile; in that last case
+    /// the longer snake survives. Snakes that swap heads both die. A dead snake stays on the
+    /// board where it died.
+    pub fn step(&mut self) {
+        if !self.is_running || self.is_game_over() {
+            return;
+        }
+
+        for snake in self.snakes.iter_mut().filter(|snake| snake.is_alive()) {
+            if let Some(direction) = snake.input_queue.pop_front() {
+                snake.movement_direction = direction;
+            }
+        }
+
+        // Where each snake's head is going, or `None` if it is not moving this tick
+        let mut targets: Vec<Option<[usize; 2]>> = vec![None; self.snakes.len()];
+        let mut deaths: Vec<Option<DeathCause>> = vec![None; self.snakes.len()];
+        for (i, snake) in self.snakes.iter().enumerate() {
+            let head = match snake.head() {
+                Some(head) if snake.is_alive() => head,
+                _ => continue,
+            };
+
+            match self.next_tile(head, snake.movement_direction) {
+                Some(tile) if self.is_obstacle(tile) => deaths[i] = Some(DeathCause::Obstacle),
+                Some(tile) => targets[i] = Some(tile),
+                None => deaths[i] = Some(DeathCause::Wall),
+            }
+        }
+
+        let is_eating: Vec<bool> = targets.iter().map(|target| target.is_some() && *target == self.food).collect();
+
+        // A snake that dies does not move, so its tail stays put and may in turn block a snake
+        // that was about to move onto it. Repeat until no more snakes die.
+        loop {
+            let mut has_changed = false;
+
+            for i in 0..self.snakes.len() {
+                let target = match targets[i] {
+                    Some(target) if deaths[i].is_none() => target,
+                    _ => continue,
+                };
+
+                if let Some(cause) = self.collision(i, target, &targets, &deaths, &is_eating) {
+                    deaths[i] = Some(cause);
+                    has_changed = true;
+                }
+            }
+
+            if !has_changed {
+                break;
+            }
+        }
+
+        self.tick += 1;
+        let mut has_eaten = false;
+        for (i, snake) in self.snakes.iter_mut().enumerate() {
+            if let Some(cause) = deaths[i] {
+                snake.death_cause = Some(cause);
+                continue;
+            }
+
+            let target = match targets[i] {
+                Some(target) => target,
+                None => continue,
+            };
+
+            snake.body.push(target);
+            snake.score.ticks += 1;
+
+            // The tail stays in place when the snake eats, which grows it by one segment
+            if is_eating[i] {
+                snake.score.food_eaten += 1;
+                has_eaten = true;
+            } else {
+                snake.body.remove(0);
+            }
+        }
+
+        if has_eaten {
+            self.spawn_food();
+        }
+    }
+
+    /// Whether snake `i` dies moving its head onto `target`, given where every snake is going
+    /// and which have already been found to die this tick
+    fn collision(
+        &self,
+        i: usize,
+        target: [usize; 2],
+        targets: &[Option<[usize; 2]>],
+        deaths: &[Option<DeathCause>],
+        is_eating: &[bool],
+    ) -> Option<DeathCause> {
+        let snake = &self.snakes[i];
+
+        for (j, other) in self.snakes.iter().enumerate() {
+            if j == i || targets[j].is_none() {
+                continue;
+            }
+
+            let is_same_tile = targets[j] == Some(target);
+            let is_swap = other.head() == Some(target) && targets[j] == snake.head();
+            if is_swap || (is_same_tile && snake.length() <= other.length()) {
+                return Some(DeathCause::HeadOn);
+            }
+        }
+
+        for (j, other) in self.snakes.iter().enumerate() {
+            // Snakes that move this tick free up their tail unless they eat; the rest stay put
+            let is_moving = targets[j].is_some() && deaths[j].is_none();
+            let blocking_body = if is_moving && !is_eating[j] { &other.body[1..] } else { &other.body[..] };
+
+            if blocking_body.contains(&target) {
+                return Some(if j == i { DeathCause::SelfCollision } else { DeathCause::SnakeCollision });
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_direction_rejects_reversal() {
+        let mut snake = Snake::new(vec![[0, 0], [0, 1], [0, 2]], MovementDirection::Right);
+
+        assert!(!snake.queue_direction(MovementDirection::Left));
+        assert!(!snake.queue_direction(MovementDirection::Right));
+        assert!(snake.queue_direction(MovementDirection::Down));
+
+        // Reversals are judged against the last queued heading, not the current one
+        assert!(!snake.queue_direction(MovementDirection::Up));
+        assert!(snake.queue_direction(MovementDirection::Left));
+        assert_eq!(snake.input_queue, vec![MovementDirection::Down, MovementDirection::Left]);
+    }
+
+    #[test]
+    fn queue_direction_is_bounded() {
+        let mut snake = Snake::new(vec![[0, 0], [0, 1], [0, 2]], MovementDirection::Right);
+        let turns = [MovementDirection::Down, MovementDirection::Left, MovementDirection::Up, MovementDirection::Right];
+
+        let accepted = turns.iter().filter(|turn| snake.queue_direction(**turn)).count();
+        assert_eq!(accepted, INPUT_QUEUE_CAPACITY);
+    }
+
+    #[test]
+    fn one_turn_is_applied_per_tick() {
+        let mut world = World::with_seed(10, 10, 1);
+        world.food = None;
+        world.snakes[0].queue_direction(MovementDirection::Down);
+        world.snakes[0].queue_direction(MovementDirection::Left);
+
+        world.step();
+        assert_eq!(world.snakes[0].head(), Some([1, 2]));
+        world.step();
+        assert_eq!(world.snakes[0].head(), Some([1, 1]));
+    }
+
+    #[test]
+    fn step_moves_the_snake_one_tile() {
+        let mut world = World::with_seed(10, 10, 1);
+        world.food = None;
+
+        world.step();
+        assert_eq!(world.snakes[0].body, vec![[0, 1], [0, 2], [0, 3]]);
+        assert_eq!(world.tick, 1);
+        assert_eq!(world.snakes[0].score.ticks, 1);
+    }
+
+    #[test]
+    fn step_grows_the_snake_when_it_eats() {
+        let mut world = World::with_seed(10, 10, 1);
+        world.food = Some([0, 3]);
+
+        world.step();
+        assert_eq!(world.snakes[0].body, vec![[0, 0], [0, 1], [0, 2], [0, 3]]);
+        assert_eq!(world.snakes[0].score.food_eaten, 1);
+        assert!(world.food.is_some_and(|food| !world.snakes[0].body.contains(&food)));
+    }
+
+    #[test]
+    fn step_wraps_or_stops_at_the_edge() {
+        let mut world = World::with_seed(4, 4, 1);
+        world.food = None;
+        world.step();
+        world.step();
+        assert_eq!(world.snakes[0].head(), Some([0, 0]));
+
+        let mut world = World::with_seed(4, 4, 1);
+        world.boundary = BoundaryMode::Walls;
+        world.food = None;
+        world.step();
+        world.step();
+        assert_eq!(world.snakes[0].death_cause, Some(DeathCause::Wall));
+        assert_eq!(world.snakes[0].head(), Some([0, 3]));
+        assert!(world.is_game_over());
+    }
+
+    #[test]
+    fn step_ends_the_game_on_self_collision() {
+        let mut world = World::with_seed(10, 10, 1);
+        world.food = None;
+        world.snakes[0] = Snake::new(vec![[1, 1], [1, 2], [1, 3], [2, 3], [2, 2]], MovementDirection::Left);
+        world.snakes[0].queue_direction(MovementDirection::Up);
+
+        world.step();
+        assert_eq!(world.snakes[0].death_cause, Some(DeathCause::SelfCollision));
+
+        // Nothing moves once the game is over
+        let tick = world.tick;
+        world.step();
+        assert_eq!(world.tick, tick);
+    }
+
+    #[test]
+    fn the_smallest_boards_fit_their_snakes() {
+        let world = World::with_players(2, START_LENGTH, 2, 1);
+        assert_eq!(world.snakes[0].body, vec![[0, 0], [0, 1], [0, 2]]);
+        assert_eq!(world.snakes[1].body, vec![[1, 2], [1, 1], [1, 0]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "too small for 1 players")]
+    fn a_board_narrower_than_a_snake_is_refused() {
+        World::with_seed(10, START_LENGTH - 1, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "too small for 3 players")]
+    fn a_board_with_fewer_rows_than_players_is_refused() {
+        World::with_players(2, 10, 3, 1);
+    }
+
+    /// Plays `ticks` ticks of a seeded game, turning on a fixed schedule, and returns the whole
+    /// world after every tick
+    fn seeded_run(seed: u64, ticks: u64) -> Vec<String> {
+        let turns = [MovementDirection::Down, MovementDirection::Right, MovementDirection::Up, MovementDirection::Right];
+        let mut world = World::with_seed(12, 16, seed);
+
+        (0..ticks)
+            .map(|tick| {
+                if tick % 5 == 4 {
+                    world.snakes[0].queue_direction(turns[(tick / 5) as usize % turns.len()]);
+                }
+                world.step();
+                serde_json::to_string(&world).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_game() {
+        assert_eq!(seeded_run(7, 200), seeded_run(7, 200));
+        assert_ne!(seeded_run(7, 200), seeded_run(8, 200));
+    }
+
+    #[test]
+    fn seeded_food_placement_is_stable() {
+        // Guards the pinned RNG: a change here breaks every recorded seed and replay
+        let foods: Vec<Option<[usize; 2]>> = [1, 2, 3].iter().map(|seed| World::with_seed(12, 16, *seed).food).collect();
+        assert_eq!(foods, vec![Some([4, 15]), Some([2, 7]), Some([1, 7])]);
+    }
+
+    /// A two-player world with the given snakes, as `(body, heading)`, and no food
+    fn duel(a: (Vec<[usize; 2]>, MovementDirection), b: (Vec<[usize; 2]>, MovementDirection)) -> World {
+        let mut world = World::with_players(10, 10, 2, 1);
+        world.snakes = vec![Snake::new(a.0, a.1), Snake::new(b.0, b.1)];
+        world.food = None;
+        world
+    }
+
+    #[test]
+    fn head_on_collision_kills_the_shorter_snake() {
+        let mut world = duel(
+            (vec![[5, 1], [5, 2], [5, 3]], MovementDirection::Right),
+            (vec![[5, 7], [5, 6], [5, 5]], MovementDirection::Left),
+        );
+        world.step();
+        assert_eq!(world.snakes[0].death_cause, Some(DeathCause::HeadOn));
+        assert_eq!(world.snakes[1].death_cause, Some(DeathCause::HeadOn));
+        assert_eq!(world.winner(), None);
+
+        let mut world = duel(
+            (vec![[5, 0], [5, 1], [5, 2], [5, 3]], MovementDirection::Right),
+            (vec![[5, 7], [5, 6], [5, 5]], MovementDirection::Left),
+        );
+        world.step();
+        assert!(world.snakes[0].is_alive());
+        assert_eq!(world.snakes[0].head(), Some([5, 4]));
+        assert_eq!(world.snakes[1].death_cause, Some(DeathCause::HeadOn));
+        assert_eq!(world.winner(), Some(0));
+    }
+
+    #[test]
+    fn swapping_heads_kills_both_snakes() {
+        let mut world = duel(
+            (vec![[5, 1], [5, 2], [5, 3], [5, 4]], MovementDirection::Right),
+            (vec![[5, 7], [5, 6], [5, 5]], MovementDirection::Left),
+        );
+        world.step();
+        assert_eq!(world.snakes[0].death_cause, Some(DeathCause::HeadOn));
+        assert_eq!(world.snakes[1].death_cause, Some(DeathCause::HeadOn));
+    }
+
+    #[test]
+    fn moving_into_a_vacating_tail_is_safe() {
+        let a = (vec![[2, 1], [2, 2], [2, 3]], MovementDirection::Down);
+        let b = (vec![[3, 3], [3, 4], [3, 5]], MovementDirection::Right);
+
+        let mut world = duel(a.clone(), b.clone());
+        world.step();
+        assert!(world.snakes.iter().all(Snake::is_alive));
+        assert_eq!(world.snakes[0].head(), Some([3, 3]));
+
+        // A snake that eats keeps its tail where it was
+        let mut world = duel(a, b);
+        world.food = Some([3, 6]);
+        world.step();
+        assert_eq!(world.snakes[0].death_cause, Some(DeathCause::SnakeCollision));
+        assert!(world.snakes[1].is_alive());
+    }
+
+    #[test]
+    fn a_dead_snake_does_not_vacate_its_tail() {
+        let mut world = duel(
+            (vec![[2, 5], [2, 6], [2, 7]], MovementDirection::Down),
+            (vec![[3, 7], [3, 8], [3, 9]], MovementDirection::Right),
+        );
+        world.boundary = BoundaryMode::Walls;
+        world.step();
+        assert_eq!(world.snakes[1].death_cause, Some(DeathCause::Wall));
+        assert_eq!(world.snakes[0].death_cause, Some(DeathCause::SnakeCollision));
+    }
+}
@@ -0,0 +1,105 @@
+//! Drives the mock Battlesnake bot over HTTP on localhost.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use rust_snake::battlesnake::{Game, GameState, MoveResponse, DEFAULT_TIMEOUT};
+use rust_snake::{BoundaryMode, MovementDirection, World};
+
+/// The bot, killed when the test ends however it ends
+struct MockBot {
+    child: Child,
+    address: String,
+}
+
+impl MockBot {
+    fn start() -> MockBot {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rust-snake-mock-bot"))
+            .args(["--bind", "127.0.0.1:0"])
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut line = String::new();
+        BufReader::new(child.stderr.as_mut().unwrap()).read_line(&mut line).unwrap();
+        let address = line.trim().rsplit("http://").next().unwrap().to_string();
+        MockBot { child, address }
+    }
+
+    /// Sends `request` and returns the whole response, or an empty string if the bot hung up
+    fn send(&self, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        stream.write_all(request).unwrap();
+
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        response
+    }
+
+    fn post(&self, path: &str, body: &str) -> String {
+        let request = format!(
+            "POST {} HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            path,
+            body.len(),
+            body,
+        );
+        self.send(request.as_bytes())
+    }
+}
+
+impl Drop for MockBot {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn body(response: &str) -> &str {
+    response.split("\r\n\r\n").nth(1).unwrap_or_default()
+}
+
+#[test]
+fn move_answers_with_the_bots_choice() {
+    let bot = MockBot::start();
+
+    let mut world = World::with_seed(8, 8, 1);
+    world.boundary = BoundaryMode::Walls;
+    world.food = Some([3, 2]);
+    let game = Game::new(String::from("test"), world.boundary, None, DEFAULT_TIMEOUT).unwrap();
+    let state = serde_json::to_string(&GameState::from_view(&game, &world.view(0))).unwrap();
+
+    assert!(bot.post("/start", &state).starts_with("HTTP/1.1 200 OK"));
+    let response = bot.post("/move", &state);
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+
+    // The food is straight below the head, which sits in the top row heading right
+    let answer: MoveResponse = serde_json::from_str(body(&response)).unwrap();
+    assert_eq!(answer.direction(), Some(MovementDirection::Down));
+    assert!(bot.post("/end", &state).starts_with("HTTP/1.1 200 OK"));
+}
+
+#[test]
+fn bad_requests_are_refused() {
+    let bot = MockBot::start();
+
+    assert!(bot.post("/move", "not json").starts_with("HTTP/1.1 400 Bad Request"));
+
+    // An absurd length is refused without waiting for, or making room for, the body
+    let response = bot.send(b"POST /move HTTP/1.1\r\nContent-Length: 1099511627776\r\n\r\n{}");
+    assert_eq!(response, "");
+
+    assert!(bot.send(b"GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 200 OK"));
+}
+
+#[test]
+fn a_silent_client_does_not_hold_up_the_bot() {
+    let bot = MockBot::start();
+
+    let _silent = TcpStream::connect(&bot.address).unwrap();
+    let started = Instant::now();
+    assert!(bot.send(b"GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 200 OK"));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,108 @@
+"""Smoke tests for the Python extension module.
+
+Build and install it first with `maturin develop`, then run `pytest`. Without pytest, run this
+file with python3 instead. Tests that need NumPy are skipped when it is missing.
+"""
+
+import unittest
+
+import rust_snake
+
+try:
+    import numpy
+except ImportError:
+    numpy = None
+
+
+def require_numpy():
+    if numpy is None:
+        raise unittest.SkipTest("needs NumPy")
+
+
+def test_world_steps_and_reports_its_state():
+    world = rust_snake.World(rows=8, cols=12, boundary="walls", seed=1)
+    assert world.shape == (8, 12)
+    assert world.seed == 1
+
+    world.step()
+    world.step(rust_snake.ACTIONS.index("down"))
+    state = world.state()
+    assert state["tick"] == 2
+    assert len(state["snakes"]) == 1
+    assert not world.is_game_over()
+
+
+def test_same_seed_gives_same_game():
+    first = rust_snake.World(seed=7)
+    second = rust_snake.World(seed=7)
+    for _ in range(5):
+        first.step()
+        second.step()
+    assert first.state() == second.state()
+
+
+def test_world_runs_into_the_wall():
+    world = rust_snake.World(rows=8, cols=12, boundary="walls", seed=1)
+    world.step(rust_snake.ACTIONS.index("up"))
+    assert world.is_game_over()
+
+
+def test_bad_arguments_raise_value_error():
+    for kwargs in ({"boundary": "sideways"}, {"rows": 0}):
+        try:
+            rust_snake.World(**kwargs)
+        except ValueError:
+            pass
+        else:
+            raise AssertionError("World(**{}) should have failed".format(kwargs))
+
+    world = rust_snake.World()
+    try:
+        world.step(len(rust_snake.ACTIONS))
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("an action out of range should have failed")
+
+
+def test_env_reset_and_step():
+    require_numpy()
+    env = rust_snake.Env(observation="compact", rows=8, cols=12, boundary="walls", seed=1)
+    observation = env.reset()
+    assert list(observation.shape) == env.observation_shape
+
+    observation, reward, done, info = env.step(rust_snake.ACTIONS.index("up"))
+    assert reward == -1.0
+    assert done
+    assert info["death_cause"] is not None
+
+
+def test_env_without_numpy_raises_import_error():
+    if numpy is not None:
+        raise unittest.SkipTest("NumPy is installed")
+    env = rust_snake.Env(rows=8, cols=12, seed=1)
+    try:
+        env.reset()
+    except ImportError:
+        pass
+    else:
+        raise AssertionError("observations should need NumPy")
+
+
+def test_vec_env_stacks_observations():
+    require_numpy()
+    envs = rust_snake.VecEnv(3, observation="grid", rows=8, cols=12, seed=1)
+    observations = envs.reset()
+    assert observations.shape == (3, len(rust_snake.GRID_CHANNELS), 8, 12)
+    assert envs.render(0).shape == (8, 12, 3)
+
+
+if __name__ == "__main__":
+    for name, test in sorted(globals().items()):
+        if name.startswith("test_"):
+            try:
+                test()
+            except unittest.SkipTest as skip:
+                print("{} skipped: {}".format(name, skip))
+            else:
+                print("{} passed".format(name))
@@ -0,0 +1,109 @@
+//! Plays networked games on localhost between a server thread and headless clients.
+
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_snake::net::{self, ClientMessage, Connection, ServerMessage, Snapshot};
+use rust_snake::server::{self, ServerOptions};
+use rust_snake::{BoundaryMode, Config, MovementDirection};
+
+/// Long enough for a short game on a slow machine, short enough that a stalled server fails fast
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A two-player game on a small walled board, where both snakes run into the far wall after a
+/// few ticks unless someone steers
+fn config() -> Config {
+    Config {
+        row_count: 6,
+        col_count: 10,
+        boundary: BoundaryMode::Walls,
+        players: 2,
+        tick_rate: Some(20.0),
+        seed: Some(1),
+        ..Config::default()
+    }
+}
+
+/// Starts a server for one game on a free local port, returning its address
+fn start_server(config: Config) -> (String, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let options = ServerOptions { rounds: Some(1), round_break: Duration::from_millis(10) };
+    let server = thread::spawn(move || server::serve(listener, &config, &options).unwrap());
+    (address, server)
+}
+
+/// Every snapshot the client receives until the server closes the connection
+fn snapshots(connection: &mut Connection) -> Vec<Snapshot> {
+    let mut snapshots = Vec::new();
+    while let Ok(Some(message)) = connection.receive() {
+        if let ServerMessage::Snapshot(snapshot) = message {
+            snapshots.push(snapshot);
+        }
+    }
+    snapshots
+}
+
+/// Runs `test` on another thread and fails if it does not finish in time
+fn with_timeout<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(test: F) -> T {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || sender.send(test()));
+    receiver.recv_timeout(TIMEOUT).expect("the game did not finish in time")
+}
+
+#[test]
+fn two_clients_play_a_game_to_the_end() {
+    let (address, server) = start_server(config());
+
+    let (first, second) = with_timeout(move || {
+        let mut first = Connection::join(&address, "first").unwrap();
+        let mut second = Connection::join(&address, "second").unwrap();
+        assert_eq!((first.player, second.player), (0, 1));
+        assert_eq!(first.config.players, 2);
+
+        let second = thread::spawn(move || snapshots(&mut second));
+        (snapshots(&mut first), second.join().unwrap())
+    });
+    server.join().unwrap();
+
+    for snapshots in [&first, &second].iter() {
+        let last = snapshots.last().expect("the client received snapshots");
+        assert!(last.snakes.iter().all(|snake| !snake.is_alive()));
+        assert!(snapshots.windows(2).all(|pair| pair[0].tick <= pair[1].tick));
+
+        let names: Vec<Option<String>> = last.players.iter().map(|player| player.name.clone()).collect();
+        assert_eq!(names, vec![Some(String::from("first")), Some(String::from("second"))]);
+    }
+    assert_eq!(first.last().map(|snapshot| snapshot.tick), second.last().map(|snapshot| snapshot.tick));
+}
+
+#[test]
+fn flooding_client_does_not_stall_the_game() {
+    let (address, server) = start_server(config());
+
+    let (started, finished) = with_timeout(move || {
+        let mut first = Connection::join(&address, "flood").unwrap();
+        let mut second = Connection::join(&address, "calm").unwrap();
+
+        // Send the heading the snake already has as fast as possible, which changes nothing
+        // in the game but keeps the server busy reading
+        let mut flood = first.sender().unwrap();
+        thread::spawn(move || {
+            let input = ClientMessage::Input { direction: MovementDirection::Right, tick: 0 };
+            while net::write_message(&mut flood, &input).is_ok() {}
+        });
+        let first = thread::spawn(move || snapshots(&mut first));
+
+        let started = Instant::now();
+        let snapshots = snapshots(&mut second);
+        let _ = first.join();
+        (started, snapshots.last().map(|snapshot| snapshot.tick))
+    });
+    server.join().unwrap();
+
+    // The snakes hit the wall within a few ticks, at 20 ticks per second
+    assert!(finished.is_some_and(|tick| tick > 0));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}